@@ -3,6 +3,11 @@
 //!
 //! See [TypeAlias], [CONSTANT], and [`module::F`].
 //!
+//! Also see the [Rust website](https://www.rust-lang.org), <https://docs.rs>,
+//! and [mail] someone.
+//!
+//! [mail]: mailto:someone@example.com
+//!
 //! `Some code`
 //!
 //! ```rust
@@ -15,6 +20,8 @@
 //!
 //! # Some funny business
 //!
+//! ## Strikethrough
+//!
 //! this should be ~struck through~
 //! this should be ~~struck through~~
 
@@ -67,6 +74,11 @@ impl T {
 
 impl T {
     pub fn yetanother() {}
+
+    /// T::asdf docs
+    pub fn asdf(&self) -> u8 {
+        self.asdf
+    }
 }
 
 /// impl F for T docs
@@ -106,6 +118,7 @@ pub enum Q {
 }
 
 /// G docs
+#[doc(alias = "T", alias = "Gee")]
 pub struct G<
     'a,
     'b,
@@ -132,7 +145,186 @@ macro_rules! decl_macro {
 }
 
 /// root_fn docs
+///
+/// - a list item that goes on long enough to wrap on narrow terminals, which
+///   should stay lined up with the start of its text
+/// - an item with more to it
+///
+///   ```
+///   let nested = "code";
+///   ```
+///
+///   1. a numbered
+///   2. sublist
+///
+/// > a block quote
+/// >
+/// > - with a list
+///
+/// And a paragraph after them.[^after]
+///
+/// | Left | Center | Right |
+/// |------|:------:|------:|
+/// | `a`  | *b*    | 1     |
+/// | long cell | c | 22[^after] |
+/// | = | assignment | 3 |
+///
+/// - [x] a finished task
+/// - [ ] and an open one
+///
+/// [^after]: A footnote, with a [link](G).
 pub fn root_fn<const N: usize>(f: u8, g: u8) {
     drop(f);
     drop(g);
 }
+
+/// hrtb_fn docs
+pub fn hrtb_fn<F, G: for<'a> Fn(&'a str) -> &'a str>(f: F, g: G)
+where
+    for<'b> F: Fn(&'b u8),
+{
+    drop(f);
+    drop(g);
+}
+
+/// bindings_fn docs
+pub fn bindings_fn(
+    it: impl Iterator<Item = u8>,
+    f: Box<dyn Fn() -> u8 + Send>,
+) -> impl IntoIterator<Item: std::fmt::Display, IntoIter: ExactSizeIterator> {
+    drop(f);
+    it.collect::<Vec<_>>()
+}
+
+/// Lending docs
+pub trait Lending {
+    /// Lending::Item docs
+    type Item<'a>
+    where
+        Self: 'a;
+}
+
+/// lend docs
+pub fn lend<'a, L: Lending>(item: <L as Lending>::Item<'a>) -> <L as Lending>::Item<'a> {
+    item
+}
+
+/// results docs
+pub fn results(a: std::io::Result<()>, b: std::fmt::Result) -> Option<std::fmt::Error> {
+    drop(a);
+    b.err()
+}
+
+/// shapes docs, whose shapes are re-exported
+pub mod shapes {
+    mod round {
+        /// Circle docs
+        pub struct Circle;
+    }
+
+    /// square docs
+    pub mod square {
+        /// Square docs
+        pub struct Square;
+    }
+
+    pub use round::Circle;
+    pub use square::Square;
+}
+
+use std::io;
+use std::ops::Deref;
+
+/// reexports docs
+pub fn reexports(circle: shapes::Circle, square: shapes::Square) -> io::Result<Box<dyn Deref<Target = u8>>> {
+    drop((circle, square));
+    Ok(Box::new(Box::new(0)))
+}
+
+/// Wrapper docs, around a [T].
+///
+/// ```
+/// let wrapper = reference::Wrapper(reference::T);
+pub struct Wrapper(pub T);
+
+impl std::ops::Deref for Wrapper {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Text docs
+pub struct Text(pub String);
+
+impl std::ops::Deref for Text {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.0
+    }
+}
+
+/// Bytes docs
+pub struct Bytes(pub Vec<u8>);
+
+impl std::ops::Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Cell docs
+pub struct Cell<V>(pub V);
+
+impl Cell<u8> {
+    /// Makes a cell of a byte.
+    pub fn new() -> Self {
+        Cell(0)
+    }
+}
+
+impl Cell<u16> {
+    /// Makes a cell of two bytes.
+    pub fn new() -> Self {
+        Cell(0)
+    }
+}
+
+/// Reads a byte without checking the bounds.
+///
+/// # Examples
+///
+/// ```
+/// let bytes: [u8; 3] = [1, 2, 3];
+/// // the second byte, at index 1
+/// assert_eq!(unsafe { reference::read(&bytes, 1) }, 2);
+/// let text = String::from("fn /* not a comment */");
+/// ```
+///
+/// ## Vectors
+///
+/// It can't read from a `Vec` by value:
+///
+/// ```compile_fail,E0308,edition2021
+/// # let bytes = vec![1, 2, 3];
+/// ##[allow(unused)]
+/// let byte = unsafe { reference::read(bytes, 1) };
+/// ```
+///
+/// ```sh
+/// # a shell comment
+/// cargo test --doc
+/// ```
+///
+/// # Panics
+///
+/// Never, but it's [undefined behaviour] to read out of bounds.
+///
+/// [undefined behaviour]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
+pub unsafe fn read(bytes: &[u8], index: usize) -> u8 {
+    *bytes.get_unchecked(index)
+}
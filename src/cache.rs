@@ -0,0 +1,171 @@
+use errata::FallibleExt;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// The name of the manifest kept in the output directory.
+const MANIFEST: &str = ".manners-cache";
+
+/// Hashes of the crates documented and the pages written by previous runs, so
+/// that unchanged crates don't get generated again, and unchanged pages don't
+/// get written (and compressed) again.
+#[derive(Default)]
+pub struct Cache {
+    dir: PathBuf,
+    /// Mixed into every hash, so that changing it invalidates every page.
+    salt: String,
+    /// The crate, hash and file name of every file in the manifest.
+    old: Vec<(String, u64, String)>,
+    /// The hashes of the pages written by this run.
+    new: HashMap<String, u64>,
+    /// The hash of what every crate in the manifest was generated from.
+    old_inputs: BTreeMap<String, u64>,
+    /// The same, for the crates of this run.
+    new_inputs: BTreeMap<String, u64>,
+}
+
+impl Cache {
+    /// Reads the manifest of `dir`, if there is one. Pages only stay cached
+    /// as long as the `salt` stays the same.
+    pub fn load(dir: &Path, salt: String) -> Self {
+        let mut old = Vec::new();
+        let mut old_inputs = BTreeMap::new();
+        for line in fs::read_to_string(dir.join(MANIFEST)).unwrap_or_default().lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(krate), Some(Ok(hash))) = (
+                fields.next(),
+                fields.next().map(|hash| u64::from_str_radix(hash, 16)),
+            ) else {
+                continue;
+            };
+
+            // the line of a crate itself has no file
+            match fields.next() {
+                Some(file) => old.push((krate.to_string(), hash, file.to_string())),
+                None => {
+                    old_inputs.insert(krate.to_string(), hash);
+                }
+            }
+        }
+
+        Self {
+            dir: dir.to_path_buf(),
+            salt,
+            old,
+            new: HashMap::new(),
+            old_inputs,
+            new_inputs: BTreeMap::new(),
+        }
+    }
+
+    /// Hashes everything that goes into a page.
+    pub fn hash(&self, page: &impl Hash) -> u64 {
+        let mut hasher = Fnv::default();
+        self.salt.hash(&mut hasher);
+        page.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns whether a file is already up to date with `hash`.
+    pub fn is_fresh(&self, file: &Path, hash: u64) -> bool {
+        let name = file_name(file);
+        file.exists()
+            && self
+                .old
+                .iter()
+                .any(|(_, old, file)| *file == name && *old == hash)
+    }
+
+    /// Records the hash of a file written by this run.
+    pub fn record(&mut self, file: &Path, hash: u64) {
+        self.new.insert(file_name(file), hash);
+    }
+
+    /// Returns whether the pages of `krate` were generated from the same
+    /// `input` (as hashed by [`Cache::hash`]) by the last run.
+    pub fn is_unchanged(&self, krate: &str, input: u64) -> bool {
+        self.old_inputs.get(krate) == Some(&input)
+    }
+
+    /// Returns the hash of a file that the last run wrote for `krate`, if it's
+    /// still there.
+    pub fn cached(&self, krate: &str, file: &Path) -> Option<u64> {
+        let name = file_name(file);
+        let (_, hash, _) = self.old.iter().find(|(k, _, f)| k == krate && *f == name)?;
+        file.exists().then_some(*hash)
+    }
+
+    /// Records what the pages of `krate` were generated from by this run.
+    pub fn record_input(&mut self, krate: &str, input: u64) {
+        self.new_inputs.insert(krate.to_string(), input);
+    }
+
+    /// Deletes the files that the previous run wrote for any of the crates in
+    /// `files`, but this one didn't, then saves the manifest. Other crates
+    /// keep their entries.
+    pub fn finish(self, files: &BTreeMap<String, BTreeSet<PathBuf>>) {
+        let written: BTreeSet<_> = files.values().flatten().map(|f| file_name(f)).collect();
+
+        let mut manifest = String::new();
+        for (krate, hash) in &self.old_inputs {
+            if !files.contains_key(krate) {
+                manifest += &format!("{krate}\t{hash:016x}\n");
+            }
+        }
+        for (krate, hash, file) in &self.old {
+            if !files.contains_key(krate) {
+                manifest += &format!("{krate}\t{hash:016x}\t{file}\n");
+            } else if !written.contains(file) {
+                eprintln!("- removing {file}");
+                if let Err(e) = fs::remove_file(self.dir.join(file)) {
+                    if e.kind() != ErrorKind::NotFound {
+                        eprintln!("failed to remove {file}: {e}");
+                    }
+                }
+            }
+        }
+
+        for (krate, files) in files {
+            if let Some(hash) = self.new_inputs.get(krate) {
+                manifest += &format!("{krate}\t{hash:016x}\n");
+            }
+            for file in files {
+                let file = file_name(file);
+                let hash = self.new.get(&file).copied().unwrap_or_default();
+                manifest += &format!("{krate}\t{hash:016x}\t{file}\n");
+            }
+        }
+
+        fs::write(self.dir.join(MANIFEST), manifest).fail("failed to write cache manifest");
+    }
+}
+
+fn file_name(file: &Path) -> String {
+    file.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its output is the same across Rust
+/// versions, which matters for hashes that outlive the process.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
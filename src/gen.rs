@@ -1,43 +1,531 @@
-use roff::{bold, italic, line_break, roman, Inline, Roff};
 use rustdoc_types::{
-    Abi, Crate, Enum, Header, Id, Impl, Item, ItemEnum, MacroKind, Module, Struct, StructKind,
-    Trait, Type, Union, Variant, VariantKind,
+    Abi, Crate, Enum, Header, Id, Impl, Item, ItemEnum, ItemKind, ItemSummary, MacroKind, Module,
+    Path, Primitive, Struct, StructKind, Trait, Type, Union, Variant, VariantKind,
 };
 
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
 use crate::markdown;
 
+pub mod format;
+use format::{bold, control, italic, line_break, roman, Inline, Page, Signature};
+
+mod error;
+pub use error::{Error, PageError};
+
 mod generics;
 use generics::*;
 
-fn get<'a>(cr: &'a Crate, id: &Id) -> &'a Item {
-    cr.index
+type Result<T = (), E = Error> = std::result::Result<T, E>;
+
+/// A generated page.
+pub struct Generated {
+    pub name: String,
+    pub page: Page,
+    /// Problems found while generating the page, in the order they were found.
+    pub warnings: Vec<String>,
+}
+
+/// Settings shared by every page of a run.
+#[derive(Clone)]
+pub struct Options {
+    /// The maximum width of documentation summary lines.
+    pub max_width: usize,
+    /// The names of every crate documented in this run, so that references
+    /// into them can point to real pages.
+    pub documented: HashSet<String>,
+    /// The features the crate was built with, if it was built by us.
+    pub features: Option<String>,
+    /// The target the crates were built for, if known.
+    pub target: Option<String>,
+    /// How the paths of types in signatures are written.
+    pub paths: PathStyle,
+    /// How the Rust code blocks in docs are highlighted.
+    pub highlight: Highlight,
+    /// The shortest public paths to the items of the crate, from
+    /// [`public_paths`].
+    pub public_paths: HashMap<Id, Vec<String>>,
+}
+
+impl Hash for Options {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // a set has no order of its own to hash in
+        let documented: BTreeSet<_> = self.documented.iter().collect();
+
+        self.max_width.hash(state);
+        documented.hash(state);
+        self.features.hash(state);
+        self.target.hash(state);
+        self.paths.hash(state);
+        self.highlight.hash(state);
+        // `public_paths` comes from the docs, which are hashed along with it
+    }
+}
+
+/// How the paths of types in signatures are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathStyle {
+    /// Just the name, like `Result`, unless another type on the page has the
+    /// same one. Then as many parent modules as it takes to tell them apart,
+    /// like `io::Result` and `fmt::Result`.
+    Short,
+    /// The full path, like `std::io::Result`.
+    Full,
+    /// Like [`PathStyle::Full`], but items of the documented crate start with
+    /// `crate` instead of its name.
+    CrateRelative,
+}
+
+impl FromStr for PathStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short" => Ok(Self::Short),
+            "full" => Ok(Self::Full),
+            "crate-relative" => Ok(Self::CrateRelative),
+            _ => Err(format!(
+                "unknown path style `{s}` (expected short, full or crate-relative)"
+            )),
+        }
+    }
+}
+
+/// How the Rust code blocks in docs are highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    /// Not at all.
+    None,
+    /// Keywords in bold, and types and comments in italic.
+    Fonts,
+    /// Like [`Highlight::Fonts`], with colors for groff to show on terminals.
+    /// Comments are set apart in blue, as terminals can't dim text.
+    Colors,
+}
+
+impl FromStr for Highlight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "fonts" => Ok(Self::Fonts),
+            "colors" => Ok(Self::Colors),
+            _ => Err(format!("unknown highlighting `{s}` (expected none, fonts or colors)")),
+        }
+    }
+}
+
+struct Context<'a> {
+    cr: &'a Crate,
+    opts: &'a Options,
+    /// The paths that have to be written out further than
+    /// [`PathStyle::Short`] would, to tell them apart.
+    qualified: HashMap<Vec<String>, String>,
+    /// The names the paths rendered so far were written as, and the full paths
+    /// behind each of them.
+    names: RefCell<HashMap<String, BTreeSet<Vec<String>>>>,
+    /// The pages referenced by the signatures rendered so far.
+    refs: RefCell<BTreeSet<String>>,
+    /// The pages linked to by the docs rendered so far, in order of their
+    /// reference numbers.
+    links: RefCell<Vec<String>>,
+    /// Problems found while rendering the page.
+    warnings: RefCell<Vec<String>>,
+    /// The `# Examples` of the docs rendered so far.
+    examples: RefCell<Vec<Inline>>,
+    /// The footnotes of the docs rendered so far, in order of their numbers.
+    notes: RefCell<Vec<Note>>,
+}
+
+/// A footnote of an item's docs.
+struct Note {
+    item: Id,
+    label: String,
+    text: Vec<Inline>,
+}
+
+impl<'a> Context<'a> {
+    fn new(cr: &'a Crate, opts: &'a Options, qualified: HashMap<Vec<String>, String>) -> Self {
+        Self {
+            cr,
+            opts,
+            qualified,
+            names: RefCell::default(),
+            refs: RefCell::default(),
+            links: RefCell::default(),
+            warnings: RefCell::default(),
+            examples: RefCell::default(),
+            notes: RefCell::default(),
+        }
+    }
+
+    /// Returns how the path to `id` is written in a signature, where it was
+    /// written as `name`.
+    fn path_name(&self, id: &Id, name: &str) -> String {
+        let Some(summary) = self.cr.paths.get(id) else {
+            return name.to_string();
+        };
+        let path = &self.public_path(summary, id, name);
+
+        let rendered = match self.opts.paths {
+            PathStyle::Short => match self.qualified.get(path) {
+                Some(qualified) => qualified.clone(),
+                None => path.last().map_or(name, String::as_str).to_string(),
+            },
+            PathStyle::Full => path.join("::"),
+            // the first segment is the name of the crate the item is from
+            PathStyle::CrateRelative if summary.crate_id == 0 => path
+                .iter()
+                .skip(1)
+                .fold("crate".to_string(), |path, segment| path + "::" + segment),
+            PathStyle::CrateRelative => path.join("::"),
+        };
+
+        self.names
+            .borrow_mut()
+            .entry(rendered.clone())
+            .or_default()
+            .insert(path.clone());
+        rendered
+    }
+
+    /// Returns the path users write for `id`, rather than the one it's defined
+    /// at, which may go through private modules. Items of other crates, whose
+    /// modules are unknown, keep the modules `name` was written with.
+    fn public_path(&self, summary: &ItemSummary, id: &Id, name: &str) -> Vec<String> {
+        let canonical = &summary.path;
+        if summary.crate_id == 0 {
+            return self.opts.public_paths.get(id).unwrap_or(canonical).clone();
+        }
+
+        let written: Vec<_> = name
+            .trim_start_matches("::")
+            .split("::")
+            .map(String::from)
+            .collect();
+        let [first, _, ..] = &written[..] else {
+            return canonical.clone();
+        };
+
+        // `io::Result` is the `Result` of the module `io` in `std::io::error::Result`
+        let modules = &canonical[..canonical.len().saturating_sub(1)];
+        if let Some(i) = modules.iter().position(|module| module == first) {
+            return [&canonical[..i], &written[..]].concat();
+        }
+
+        // `std::fmt::Result` is a path from a crate re-exporting `core::fmt::Result`
+        let crates = &self.cr.external_crates;
+        if crates.values().any(|krate| krate.name == *first) {
+            return written;
+        }
+
+        canonical.clone()
+    }
+
+    /// Finds the names that were written for several different paths, and
+    /// qualifies each of those paths just enough to tell them apart.
+    fn ambiguous(&self) -> HashMap<Vec<String>, String> {
+        let mut qualified = HashMap::new();
+        for paths in self.names.borrow().values().filter(|paths| paths.len() > 1) {
+            let longest = paths.iter().map(Vec::len).max().unwrap_or_default();
+            for n in 2..=longest {
+                let names: Vec<_> = paths
+                    .iter()
+                    .map(|path| path[path.len().saturating_sub(n)..].join("::"))
+                    .collect();
+
+                let unique = names.iter().collect::<HashSet<_>>().len() == names.len();
+                if unique || n == longest {
+                    qualified.extend(paths.iter().cloned().zip(names));
+                    break;
+                }
+            }
+        }
+
+        qualified
+    }
+
+    /// Records a problem with the page, which is still generated.
+    fn warn(&self, warning: impl Into<String>) {
+        self.warnings.borrow_mut().push(warning.into());
+    }
+
+    /// Returns the name of the page documenting `id`, if there is one.
+    fn page_name(&self, id: &Id) -> Option<String> {
+        let summary = self.cr.paths.get(id)?;
+        if summary.crate_id != 0 {
+            let krate = self.cr.external_crates.get(&summary.crate_id)?;
+            if !self.opts.documented.contains(&krate.name) {
+                return None;
+            }
+        }
+
+        let typ = match summary.kind {
+            ItemKind::Module => "mod",
+            ItemKind::Function => "fn",
+            ItemKind::Struct => "struct",
+            ItemKind::Enum => "enum",
+            ItemKind::Union => "union",
+            ItemKind::Trait => "trait",
+            ItemKind::TypeAlias => "type",
+            ItemKind::Constant => "const",
+            ItemKind::Static => "static",
+            ItemKind::Primitive => "primitive",
+            ItemKind::Macro | ItemKind::ProcAttribute | ItemKind::ProcDerive => "macro",
+            _ => return None,
+        };
+
+        Some(format!("{typ}:{}", summary.path.join("::")))
+    }
+
+    /// Records that the page references `id`, to be listed under `SEE ALSO`.
+    fn reference(&self, id: &Id) {
+        if let Some(name) = self.page_name(id) {
+            self.refs.borrow_mut().insert(name);
+        }
+    }
+
+    /// Returns the reference number of a link to `id`, numbering it if it's
+    /// new to the page.
+    fn link(&self, id: &Id) -> Option<usize> {
+        let name = self.page_name(id)?;
+        let mut links = self.links.borrow_mut();
+        let i = links.iter().position(|l| *l == name).unwrap_or_else(|| {
+            links.push(name);
+            links.len() - 1
+        });
+
+        Some(i + 1)
+    }
+
+    /// Returns the number of the footnote `label` of the docs of `id`,
+    /// numbering it if it's new to the page.
+    fn note(&self, id: &Id, label: &str, text: Vec<Inline>) -> usize {
+        let mut notes = self.notes.borrow_mut();
+        let i = notes
+            .iter()
+            .position(|n| n.item == *id && n.label == label)
+            .unwrap_or_else(|| {
+                notes.push(Note {
+                    item: id.clone(),
+                    label: label.to_string(),
+                    text,
+                });
+                notes.len() - 1
+            });
+
+        i + 1
+    }
+}
+
+/// Resolves the intra-doc links and footnotes of an item's docs.
+struct DocLinks<'a, 'b> {
+    cx: &'a Context<'b>,
+    item: &'b Item,
+}
+
+impl markdown::Links for DocLinks<'_, '_> {
+    fn destinations(&self) -> Vec<&str> {
+        self.item.links.keys().map(String::as_str).collect()
+    }
+
+    fn number(&self, destination: &str) -> Option<usize> {
+        self.cx.link(self.item.links.get(destination)?)
+    }
+
+    fn footnote(&self, label: &str, text: Vec<Inline>) -> usize {
+        self.cx.note(&self.item.id, label, text)
+    }
+}
+
+fn get<'a>(cx: &Context<'a>, id: &Id) -> Result<&'a Item> {
+    cx.cr
+        .index
         .get(id)
-        .unwrap_or_else(|| panic!("invalid ID: {}", id.0))
+        .ok_or_else(|| Error::MissingItem(id.clone()))
 }
 
-fn render_links(cr: &Crate, item: &Item, page: &mut Roff) {
-    let paths: Vec<_> = item
-        .links
-        // .values()
-        .iter()
-        .flat_map(|(_, id)| {
-            Some([
-                cr.paths.get(id).map(|i| italic(i.path.join("::")))?,
-                roman(", "),
-            ])
-        })
-        .flatten()
-        .collect();
+fn name(item: &Item) -> Result<&str> {
+    item.name
+        .as_deref()
+        .ok_or_else(|| Error::MissingName(item.id.clone()))
+}
+
+fn unexpected(id: &Id, expected: &'static str) -> Error {
+    Error::UnexpectedItem {
+        id: id.clone(),
+        expected,
+    }
+}
+
+fn render_links(cx: &Context, item: &Item, page: &mut Page) {
+    let links = cx.links.borrow();
+
+    // anything the docs didn't number (e.g. syntax we can't parse) is still
+    // worth mentioning, along with the types in the signatures
+    let mut others = cx.refs.borrow().clone();
+    others.extend(item.links.values().filter_map(|id| cx.page_name(id)));
+    others.retain(|name| !links.contains(name));
+
+    if links.is_empty() && others.is_empty() {
+        return;
+    }
+
+    page.control("SH", ["SEE ALSO"]);
+
+    for (i, name) in links.iter().enumerate() {
+        page.text([
+            roman(format!("[{}] ", i + 1)),
+            bold(name),
+            roman("(3r)"),
+            line_break(),
+        ]);
+    }
+
+    let mut buf = Vec::new();
+    for (i, name) in others.iter().enumerate() {
+        buf.push(bold(name));
+        buf.push(roman(if i + 1 < others.len() { "(3r), " } else { "(3r)" }));
+    }
+    if !buf.is_empty() {
+        page.text(buf);
+    }
+}
+
+/// Renders the docs of an item. The conventional headings of Rust docs become
+/// sections of their own; `# Examples` waits for [`render_examples`], to be
+/// near the end like in other manpages.
+fn render_docs(cx: &Context, item: &Item, page: &mut Page) {
+    let docs = item.docs.as_deref().unwrap_or_default();
+    let mut sections = item.docs.as_deref().map(markdown::sections).unwrap_or_default();
+    let definitions = markdown::definitions(docs);
+    // headings in the docs of a subsection can't be subsections themselves
+    let render = |markdown: &str, subsections: bool| {
+        let markdown = markdown::close_fence(markdown);
+        let docs = format!("{markdown}\n\n{definitions}");
+        markdown::to_roff(&docs, &DocLinks { cx, item }, cx.opts.highlight, subsections)
+    };
+
+    if let ItemEnum::Function(func) = &item.inner {
+        let safety = sections.iter().any(|s| s.heading.as_deref() == Some("Safety"));
+        if func.header.unsafe_ && !safety {
+            let name = item.name.as_deref().unwrap_or_default();
+            cx.warn(format!("unsafe fn {name} has no `# Safety` section"));
+        }
+    }
+
+    if sections.is_empty() {
+        return;
+    }
+    let intro = sections.remove(0).markdown;
+
+    if let Some((synopsis, rest)) = intro.split_once("\n\n") {
+        page.control("SH", ["SYNOPSIS"]);
+        page.text(render(synopsis, false));
+        page.control("SH", ["DESCRIPTION"]);
+        page.text(render(rest, true));
+    } else if !intro.is_empty() || sections.iter().any(|s| !is_section(s)) {
+        page.control("SH", ["DESCRIPTION"]);
+        page.text(render(&intro, true));
+    }
+
+    // anything else (`# Safety`, `# Panics`, ...) is part of the description
+    let mut errors = Vec::new();
+    for section in &sections {
+        let heading = section.heading.as_deref().unwrap_or_default();
+        let text = render(&section.markdown, is_section(section));
+        match heading.to_lowercase().as_str() {
+            "examples" | "example" => append_paragraphs(&mut cx.examples.borrow_mut(), text),
+            "errors" => append_paragraphs(&mut errors, text),
+            _ => {
+                page.control("SS", [heading]);
+                page.text(text);
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        page.control("SH", ["ERRORS"]);
+        page.text(errors);
+    }
+}
+
+/// Returns whether a section of docs gets a man section of its own, rather than
+/// a subsection of the description.
+fn is_section(section: &markdown::Section) -> bool {
+    let heading = section.heading.as_deref().unwrap_or_default().to_lowercase();
+    matches!(heading.as_str(), "examples" | "example" | "errors")
+}
+
+/// Appends rendered docs to others, as paragraphs of their own.
+fn append_paragraphs(buf: &mut Vec<Inline>, mut text: Vec<Inline>) {
+    if !buf.is_empty() {
+        buf.push(control("PP", []));
+    }
+    buf.append(&mut text);
+}
+
+/// Renders the examples of the docs rendered so far.
+fn render_examples(cx: &Context, page: &mut Page) {
+    let examples = cx.examples.take();
+    if !examples.is_empty() {
+        page.control("SH", ["EXAMPLES"]);
+        page.text(examples);
+    }
+}
 
-    if !paths.is_empty() {
-        page.control("SH", ["SEE ALSO"]);
-        page.text(&paths[..paths.len() - 1]);
+/// Renders the footnotes of the docs rendered so far.
+fn render_notes(cx: &Context, page: &mut Page) {
+    let notes = cx.notes.take();
+    if notes.is_empty() {
+        return;
+    }
+
+    page.control("SH", ["NOTES"]);
+    let width = markdown::NOTE_INDENT.to_string();
+    for (i, note) in notes.into_iter().enumerate() {
+        page.control("IP", [format!("[^{}]", i + 1).as_str(), &width]);
+        page.text(note.text);
+    }
+}
+
+/// Renders (part of) the docs of `item`, which its links are resolved against.
+fn render_markdown(cx: &Context, item: &Item, docs: &str) -> Vec<Inline> {
+    markdown::to_roff(docs, &DocLinks { cx, item }, cx.opts.highlight, false)
+}
+
+/// Renders docs in the middle of code, like those of the fields of a struct:
+/// they're filled like any other docs, indented by `indent` columns.
+fn render_nested_docs(cx: &Context, item: &Item, docs: &str, indent: usize, buf: &mut Vec<Inline>) {
+    buf.push(control("EE", []));
+    buf.push(control("RS", [indent.to_string().as_str()]));
+    buf.append(&mut render_markdown(cx, item, docs));
+    buf.push(control("RE", []));
+    buf.push(control("EX", ["rust"]));
+}
+
+/// Writes code, like signatures, line by line as it was laid out.
+fn render_code(page: &mut Page, mut buf: Vec<Inline>) {
+    trim_code(&mut buf);
+    page.control("EX", ["rust"]);
+    page.text(buf);
+    page.control("EE", []);
+}
+
+/// Drops the breaks at the end of code, which would leave a blank line.
+fn trim_code(buf: &mut Vec<Inline>) {
+    while buf.last().is_some_and(|i| *i == line_break() || *i == roman("")) {
+        buf.pop();
     }
 }
 
-fn render_items(cr: &Crate, items: &[Id], page: &mut Roff, max_width: Option<usize>) {
+fn render_items(cx: &Context, items: &[Id], page: &mut Page, max_width: Option<usize>) -> Result {
     render_item_kinds! {
-        cr, items, page, max_width;
+        cx, items, page, max_width;
         "MODULES": mod Module;
         "UNIONS": union Union;
         "STRUCTS": struct Struct;
@@ -52,9 +540,11 @@ fn render_items(cr: &Crate, items: &[Id], page: &mut Roff, max_width: Option<usi
         "PROC MACROS": macro ProcMacro;
         "PRIMITIVES": primitive Primitive;
     }
+
+    Ok(())
 }
 
-fn render_fields(cr: &Crate, kind: &StructKind, page: &mut Vec<Inline>) {
+fn render_fields(cx: &Context, kind: &StructKind, page: &mut Vec<Inline>) -> Result {
     let mut depth = 0;
     match kind {
         StructKind::Unit => {}
@@ -93,12 +583,12 @@ fn render_fields(cr: &Crate, kind: &StructKind, page: &mut Vec<Inline>) {
                 let Item {
                     inner: ItemEnum::StructField(typ),
                     ..
-                } = get(cr, field)
+                } = get(cx, field)?
                 else {
-                    unreachable!()
+                    return Err(unexpected(field, "struct field"));
                 };
 
-                render_type(cr, typ, depth, page);
+                render_type(cx, typ, depth, page)?;
             }
 
             if private {
@@ -117,70 +607,64 @@ fn render_fields(cr: &Crate, kind: &StructKind, page: &mut Vec<Inline>) {
             fields,
             fields_stripped,
         } => {
-            let sep = |page: &mut Vec<Inline>| {
-                page.push(roman(","));
-                page.push(line_break());
-                page.push(roman("  "));
-            };
-
             page.push(roman(" {"));
-            page.push(line_break());
-            page.push(roman("  "));
 
             let mut first = true;
             for field in fields {
                 if !first {
-                    sep(page);
+                    page.push(roman(","));
                 }
 
+                let field = get(cx, field)?;
                 let Item {
                     name: Some(name),
                     docs,
                     inner: ItemEnum::StructField(typ),
                     ..
-                } = get(cr, field)
+                } = field
                 else {
-                    panic!("invalid struct field");
+                    return Err(unexpected(&field.id, "named struct field"));
                 };
 
                 if let Some(docs) = docs {
-                    if !first {
-                        page.push(line_break());
-                        page.push(roman("    "));
-                    } else {
-                        page.push(roman("  "));
-                    }
-                    page.append(&mut markdown::to_roff(docs, 2));
-                    page.push(roman("  "));
+                    render_nested_docs(cx, field, docs, 4, page);
+                } else {
+                    page.push(line_break());
                 }
 
                 first = false;
 
+                page.push(roman("  "));
                 page.push(roman(name));
                 page.push(roman(": "));
-                render_type(cr, typ, 1, page);
+                render_type(cx, typ, 1, page)?;
             }
 
             if *fields_stripped {
                 if !fields.is_empty() {
-                    sep(page);
+                    page.push(roman(","));
                 }
-                page.push(roman("/* private fields */"));
+                page.push(line_break());
+                page.push(roman("  /* private fields */"));
             }
 
             page.push(line_break());
             page.push(roman("}"));
         }
     }
+
+    Ok(())
 }
 
-fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>) {
+fn render_type(cx: &Context, ty: &Type, mut depth: usize, page: &mut Vec<Inline>) -> Result {
     match ty {
         Type::ResolvedPath(path) => {
+            cx.reference(&path.id);
+            let name = cx.path_name(&path.id, &path.name);
             if let Some(args) = &path.args {
-                render_generics_args(cr, &path.name, args, depth, page);
+                render_generics_args(cx, &name, args, depth, page)?;
             } else {
-                page.push(bold(&path.name));
+                page.push(bold(name));
             }
         }
         Type::DynTrait(obj) => {
@@ -193,14 +677,16 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
                 first = false;
 
                 if !tr.generic_params.is_empty() {
-                    render_generics(cr, "for", &tr.generic_params, depth, page);
+                    render_generics(cx, "for", &tr.generic_params, depth, page)?;
                     page.push(roman(" "));
                 }
 
+                cx.reference(&tr.trait_.id);
+                let name = cx.path_name(&tr.trait_.id, &tr.trait_.name);
                 if let Some(args) = &tr.trait_.args {
-                    render_generics_args(cr, &tr.trait_.name, args, depth, page);
+                    render_generics_args(cx, &name, args, depth, page)?;
                 } else {
-                    page.push(bold(&tr.trait_.name));
+                    page.push(bold(name));
                 }
             }
 
@@ -212,45 +698,13 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
         Type::Generic(s) | Type::Primitive(s) => page.push(bold(s)),
         Type::FunctionPointer(func) => {
             if !func.generic_params.is_empty() {
-                render_generics(cr, "for", &func.generic_params, depth, page);
-                page.push(roman(" "));
-            }
-
-            let Header {
-                const_,
-                unsafe_,
-                async_,
-                ..
-            } = &func.header;
-            if *const_ {
-                page.push(roman("const "));
-            }
-            if *unsafe_ {
-                page.push(roman("unsafe "));
-            }
-            if *async_ {
-                page.push(roman("async "));
-            }
-
-            if let Some(abi) = match &func.header.abi {
-                Abi::Rust => None,
-                Abi::C { .. } => Some("\"C\""),
-                Abi::Cdecl { .. } => Some("\"cdecl\""),
-                Abi::Stdcall { .. } => Some("\"stdcall\""),
-                Abi::Fastcall { .. } => Some("\"fastcall\""),
-                Abi::Aapcs { .. } => Some("\"aapcs\""),
-                Abi::Win64 { .. } => Some("\"win64\""),
-                Abi::SysV64 { .. } => Some("\"sysv64\""),
-                Abi::System { .. } => Some("\"system\""),
-                Abi::Other(s) => Some(&**s),
-            } {
-                page.push(roman(abi));
+                render_generics(cx, "for", &func.generic_params, depth, page)?;
                 page.push(roman(" "));
             }
 
             let inputs = &func.decl.inputs;
 
-            page.push(roman("fn("));
+            page.push(roman(qualifiers(&func.header) + "("));
             if inputs.len() >= 3 {
                 depth += 1;
                 page.push(line_break());
@@ -273,7 +727,7 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
                     sep(page, depth + 1);
                 }
                 first = false;
-                render_type(cr, arg, depth + 1, page);
+                render_type(cx, arg, depth + 1, page)?;
             }
 
             if func.decl.c_variadic {
@@ -290,7 +744,7 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
 
             if let Some(output) = &func.decl.output {
                 page.push(roman(" -> "));
-                render_type(cr, output, depth + 1, page);
+                render_type(cx, output, depth + 1, page)?;
             }
         }
         Type::Tuple(types) => {
@@ -317,7 +771,7 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
                     sep(page, depth + 1);
                 }
                 first = false;
-                render_type(cr, arg, depth + 1, page);
+                render_type(cx, arg, depth + 1, page)?;
             }
 
             if types.len() < 3 {
@@ -329,23 +783,31 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
         }
         Type::Slice(typ) => {
             page.push(roman("["));
-            render_type(cr, typ, depth, page);
+            render_type(cx, typ, depth, page)?;
             page.push(roman("]"));
         }
         Type::Array { type_, len } => {
             page.push(roman("["));
-            render_type(cr, type_, depth, page);
+            render_type(cx, type_, depth, page)?;
             page.push(roman(format!("; {len}]")));
         }
-        Type::Pat { .. } => eprintln!("pattern types aren't supported (yet)"),
+        Type::Pat {
+            type_,
+            __pat_unstable_do_not_use: pat,
+        } => {
+            // `u32 is 1..`
+            render_type(cx, type_, depth, page)?;
+            page.push(roman(" is "));
+            page.push(roman(pat));
+        }
         Type::ImplTrait(traits) => {
             page.push(roman("impl "));
-            render_generics_bounds(cr, traits, depth, page);
+            render_generics_bounds(cx, traits, depth, page)?;
         }
         Type::Infer => page.push(roman("_")),
         Type::RawPointer { mutable, type_ } => {
             page.push(roman(if *mutable { "*mut " } else { "*const " }));
-            render_type(cr, type_, depth, page);
+            render_type(cx, type_, depth, page)?;
         }
         Type::BorrowedRef {
             lifetime,
@@ -360,87 +822,50 @@ fn render_type(cr: &Crate, ty: &Type, mut depth: usize, page: &mut Vec<Inline>)
             if *mutable {
                 page.push(roman("mut "));
             }
-            render_type(cr, type_, depth, page);
+            render_type(cx, type_, depth, page)?;
         }
-        // TODO: what is args?
         Type::QualifiedPath {
             name,
-            args: _,
+            args,
             self_type,
             trait_,
         } => {
             if let Some(tr) = trait_ {
+                cx.reference(&tr.id);
                 page.push(roman("<"));
-                render_type(cr, self_type, depth, page);
+                render_type(cx, self_type, depth, page)?;
                 page.push(roman(" as "));
+                let name = cx.path_name(&tr.id, &tr.name);
                 if let Some(args) = &tr.args {
-                    render_generics_args(cr, &tr.name, args, depth, page);
+                    render_generics_args(cx, &name, args, depth, page)?;
                 } else {
-                    page.push(bold(&tr.name));
+                    page.push(bold(name));
                 }
                 page.push(roman(">::"));
-                page.push(italic(name));
             } else {
-                render_type(cr, self_type, depth, page);
+                render_type(cx, self_type, depth, page)?;
                 page.push(roman("::"));
-                page.push(italic(name));
             }
+
+            // `Item<'a>` of a generic associated type
+            page.push(italic(name));
+            render_args(cx, args, depth, page)?;
         }
     }
+
+    Ok(())
 }
 
-fn render_fn(cr: &Crate, id: &Id, mut depth: usize, page: &mut Vec<Inline>) {
-    let Item {
-        inner: ItemEnum::Function(func),
-        name,
-        ..
-    } = get(cr, id)
-    else {
-        unreachable!()
+fn render_fn(cx: &Context, id: &Id, mut depth: usize, page: &mut Vec<Inline>) -> Result {
+    let item = get(cx, id)?;
+    let ItemEnum::Function(func) = &item.inner else {
+        return Err(unexpected(id, "function"));
     };
 
-    let Header {
-        const_,
-        unsafe_,
-        async_,
-        ..
-    } = &func.header;
-    if *const_ {
-        page.push(roman("const "));
-    }
-    if *unsafe_ {
-        page.push(roman("unsafe "));
-    }
-    if *async_ {
-        page.push(roman("async "));
-    }
-
-    if let Some(abi) = match &func.header.abi {
-        Abi::Rust => None,
-        Abi::C { .. } => Some("\"C\""),
-        Abi::Cdecl { .. } => Some("\"cdecl\""),
-        Abi::Stdcall { .. } => Some("\"stdcall\""),
-        Abi::Fastcall { .. } => Some("\"fastcall\""),
-        Abi::Aapcs { .. } => Some("\"aapcs\""),
-        Abi::Win64 { .. } => Some("\"win64\""),
-        Abi::SysV64 { .. } => Some("\"sysv64\""),
-        Abi::System { .. } => Some("\"system\""),
-        Abi::Other(s) => Some(&**s),
-    } {
-        page.push(roman(abi));
-        page.push(roman(" "));
-    }
-
     let inputs = &func.decl.inputs;
 
-    page.push(roman("fn "));
-    render_generics(
-        cr,
-        name.as_ref().unwrap(),
-        &func.generics.params,
-        depth,
-        page,
-    );
+    page.push(roman(qualifiers(&func.header) + " "));
+    render_generics(cx, name(item)?, &func.generics.params, depth, page)?;
     page.push(roman("("));
     if inputs.len() >= 3 {
         depth += 1;
@@ -467,7 +892,7 @@ fn render_fn(cr: &Crate, id: &Id, mut depth: usize, page: &mut Vec<Inline>) {
 
         page.push(roman(name));
         page.push(roman(": "));
-        render_type(cr, arg, depth + 1, page);
+        render_type(cx, arg, depth + 1, page)?;
     }
 
     if func.decl.c_variadic {
@@ -484,15 +909,112 @@ fn render_fn(cr: &Crate, id: &Id, mut depth: usize, page: &mut Vec<Inline>) {
 
     if let Some(output) = &func.decl.output {
         page.push(roman(" -> "));
-        render_type(cr, output, depth + 1, page);
+        render_type(cx, output, depth + 1, page)?;
     }
 
     page.push(line_break());
     page.push(roman("  ".repeat(depth)));
-    render_where(cr, &func.generics.where_predicates, depth + 1, page);
+    render_where(cx, &func.generics.where_predicates, depth + 1, page)?;
+
+    Ok(())
+}
+
+/// Returns what comes before the name of a function, or the arguments of a
+/// function pointer, like `const unsafe fn`.
+fn qualifiers(header: &Header) -> String {
+    let Header {
+        const_,
+        unsafe_,
+        async_,
+        abi,
+    } = header;
+
+    let mut qualifiers = String::new();
+    if *const_ {
+        qualifiers += "const ";
+    }
+    if *unsafe_ {
+        qualifiers += "unsafe ";
+    }
+    if *async_ {
+        qualifiers += "async ";
+    }
+
+    if let Some(abi) = match abi {
+        Abi::Rust => None,
+        Abi::C { .. } => Some("\"C\""),
+        Abi::Cdecl { .. } => Some("\"cdecl\""),
+        Abi::Stdcall { .. } => Some("\"stdcall\""),
+        Abi::Fastcall { .. } => Some("\"fastcall\""),
+        Abi::Aapcs { .. } => Some("\"aapcs\""),
+        Abi::Win64 { .. } => Some("\"win64\""),
+        Abi::SysV64 { .. } => Some("\"sysv64\""),
+        Abi::System { .. } => Some("\"system\""),
+        Abi::Other(s) => Some(&**s),
+    } {
+        qualifiers += abi;
+        qualifiers += " ";
+    }
+
+    qualifiers + "fn"
+}
+
+/// Writes the signature of a function on a page of its own, with its parts
+/// told apart for mdoc(7).
+fn render_signature(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let item = get(cx, id)?;
+    let ItemEnum::Function(func) = &item.inner else {
+        return Err(unexpected(id, "function"));
+    };
+
+    let mut code = Vec::new();
+    render_fn(cx, id, 0, &mut code)?;
+    trim_code(&mut code);
+
+    // each part on one line, as the macros fill them anyway
+    let plain = |render: &dyn Fn(&mut Vec<Inline>) -> Result| -> Result<String> {
+        let mut buf = Vec::new();
+        render(&mut buf)?;
+        Ok(plain_text(&buf))
+    };
+
+    let generics = &func.generics;
+    let mut args = Vec::new();
+    for (name, ty) in &func.decl.inputs {
+        args.push(format!("{name}: {}", plain(&|buf| render_type(cx, ty, 0, buf))?));
+    }
+    if func.decl.c_variadic {
+        args.push("...".to_string());
+    }
+
+    page.signature(Signature {
+        code,
+        qualifiers: qualifiers(&func.header),
+        name: plain(&|buf| render_generics(cx, name(item)?, &generics.params, 0, buf))?,
+        args,
+        output: match &func.decl.output {
+            Some(output) => format!("-> {}", plain(&|buf| render_type(cx, output, 0, buf))?),
+            None => String::new(),
+        },
+        bounds: plain(&|buf| render_where(cx, &generics.where_predicates, 0, buf))?,
+    });
+
+    Ok(())
 }
 
-fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
+/// Returns rendered code as plain text, on one line.
+fn plain_text(buf: &[Inline]) -> String {
+    let text: String = buf
+        .iter()
+        .map(|inline| match inline {
+            Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => s.as_str(),
+            _ => " ",
+        })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn render_impls(cx: &Context, impls: &[Id], page: &mut Page) -> Result {
     let mut buf = Vec::new();
 
     let mut first = true;
@@ -500,14 +1022,15 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
         let Item {
             inner: ItemEnum::Impl(imp),
             ..
-        } = get(cr, id)
+        } = get(cx, id)?
         else {
-            unreachable!()
+            return Err(unexpected(id, "impl"));
         };
         if !imp.synthetic && imp.blanket_impl.is_none() {
             if first {
-                page.text(buf.clone());
-                buf.clear();
+                if !buf.is_empty() {
+                    render_code(page, std::mem::take(&mut buf));
+                }
                 page.control("SH", ["IMPLS"]);
                 first = false;
             } else {
@@ -519,7 +1042,7 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
                 ]);
             }
 
-            render_impl(cr, imp, true, &mut buf);
+            render_impl(cx, imp, true, &mut buf)?;
         }
     }
 
@@ -528,14 +1051,15 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
         let Item {
             inner: ItemEnum::Impl(imp),
             ..
-        } = get(cr, id)
+        } = get(cx, id)?
         else {
-            unreachable!()
+            return Err(unexpected(id, "impl"));
         };
         if imp.blanket_impl.is_some() {
             if first {
-                page.text(buf.clone());
-                buf.clear();
+                if !buf.is_empty() {
+                    render_code(page, std::mem::take(&mut buf));
+                }
                 page.control("SH", ["BLANKET IMPLS"]);
                 first = false;
             } else {
@@ -547,7 +1071,7 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
                 ]);
             }
 
-            render_impl(cr, imp, false, &mut buf);
+            render_impl(cx, imp, false, &mut buf)?;
         }
     }
 
@@ -556,14 +1080,15 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
         let Item {
             inner: ItemEnum::Impl(imp),
             ..
-        } = get(cr, id)
+        } = get(cx, id)?
         else {
-            unreachable!()
+            return Err(unexpected(id, "impl"));
         };
         if imp.blanket_impl.is_none() && imp.synthetic {
             if first {
-                page.text(buf.clone());
-                buf.clear();
+                if !buf.is_empty() {
+                    render_code(page, std::mem::take(&mut buf));
+                }
                 page.control("SH", ["AUTO TRAIT IMPLS"]);
                 first = false;
             } else {
@@ -575,119 +1100,267 @@ fn render_impls(cr: &Crate, impls: &[Id], page: &mut Roff) {
                 ]);
             }
 
-            render_impl(cr, imp, false, &mut buf);
+            render_impl(cx, imp, false, &mut buf)?;
         }
     }
 
-    page.text(buf);
-}
-
-fn render_impl(cr: &Crate, imp: &Impl, render_items: bool, page: &mut Vec<Inline>) {
-    if imp.is_unsafe {
-        page.push(roman("unsafe "));
+    if !buf.is_empty() {
+        render_code(page, buf);
     }
 
-    page.push(roman("impl"));
-    render_generics(cr, "", &imp.generics.params, 0, page);
+    render_deref(cx, impls, page)?;
 
-    page.push(roman(" "));
+    Ok(())
+}
+
+/// Renders the methods a type gets through its `Deref` impl, if it has one.
+fn render_deref(cx: &Context, impls: &[Id], page: &mut Page) -> Result {
+    for id in impls {
+        let ItemEnum::Impl(imp) = &get(cx, id)?.inner else {
+            return Err(unexpected(id, "impl"));
+        };
+
+        let Some(tr) = &imp.trait_ else {
+            continue;
+        };
+        let is_deref = cx
+            .cr
+            .paths
+            .get(&tr.id)
+            .is_some_and(|summary| summary.path.join("::") == "core::ops::deref::Deref");
+        if !is_deref {
+            continue;
+        }
+
+        // `type Target = ...;`
+        let target = imp
+            .items
+            .iter()
+            .filter_map(|id| cx.cr.index.get(id))
+            .find_map(|item| match &item.inner {
+                ItemEnum::AssocType {
+                    default: Some(target),
+                    ..
+                } if item.name.as_deref() == Some("Target") => Some(target),
+                _ => None,
+            });
+
+        let (item, krate) = match target {
+            Some(Type::ResolvedPath(path)) => {
+                let krate = cx
+                    .cr
+                    .paths
+                    .get(&path.id)
+                    .and_then(|summary| cx.cr.external_crates.get(&summary.crate_id))
+                    .map_or("another", |krate| &krate.name);
+                (cx.cr.index.get(&path.id), krate)
+            }
+            Some(Type::Primitive(name)) => primitive(cx, name),
+            Some(Type::Slice(_)) => primitive(cx, "slice"),
+            Some(Type::Array { .. }) => primitive(cx, "array"),
+            // generic targets, like that of `Box<T>`, have no methods to show
+            _ => continue,
+        };
+
+        let mut buf = vec![bold("Deref"), roman("<"), italic("Target"), roman(" = ")];
+        if let Some(target) = target {
+            render_type(cx, target, 0, &mut buf)?;
+        }
+        buf.extend_from_slice(&[roman(">"), line_break()]);
+
+        let Some(item) = item else {
+            page.control("SH", ["METHODS FROM DEREF"]);
+            render_code(page, buf);
+            page.control("PP", []);
+            page.text([
+                roman("The methods of the target are documented in the "),
+                italic(krate),
+                roman(" crate."),
+            ]);
+            continue;
+        };
+
+        let methods = deref_methods(cx, item);
+        if methods.is_empty() {
+            continue;
+        }
+
+        page.control("SH", ["METHODS FROM DEREF"]);
+        render_assoc_items(cx, &methods, &mut buf)?;
+        render_code(page, buf);
+    }
+
+    Ok(())
+}
+
+/// Returns the primitive type `name`, like `u8` or `slice`, if this crate
+/// documents it, and the crate that does otherwise.
+fn primitive<'a>(cx: &Context<'a>, name: &str) -> (Option<&'a Item>, &'a str) {
+    let item = cx.cr.index.values().find(|item| match &item.inner {
+        ItemEnum::Primitive(primitive) => primitive.name == name,
+        _ => false,
+    });
+
+    // both `core` and `std` have it, so pick the same one on every run
+    let krate = cx
+        .cr
+        .paths
+        .values()
+        .filter(|summary| {
+            summary.kind == ItemKind::Primitive && summary.path.last().is_some_and(|n| n == name)
+        })
+        .filter_map(|summary| cx.cr.external_crates.get(&summary.crate_id))
+        .map(|krate| krate.name.as_str())
+        .min()
+        .unwrap_or("core");
+
+    (item, krate)
+}
+
+/// Returns the methods of a type's inherent impls that can be called through a
+/// reference to it: those taking `self`.
+fn deref_methods(cx: &Context, item: &Item) -> Vec<Id> {
+    let impls = match &item.inner {
+        ItemEnum::Struct(Struct { impls, .. })
+        | ItemEnum::Enum(Enum { impls, .. })
+        | ItemEnum::Union(Union { impls, .. })
+        | ItemEnum::Primitive(Primitive { impls, .. }) => impls,
+        _ => return Vec::new(),
+    };
+
+    impls
+        .iter()
+        .filter_map(|id| match &cx.cr.index.get(id)?.inner {
+            ItemEnum::Impl(imp) if imp.trait_.is_none() => Some(&imp.items),
+            _ => None,
+        })
+        .flatten()
+        .filter(|id| {
+            cx.cr.index.get(id).is_some_and(|item| match &item.inner {
+                ItemEnum::Function(func) => {
+                    func.decl.inputs.first().is_some_and(|(name, _)| name == "self")
+                }
+                _ => false,
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+fn render_impl(cx: &Context, imp: &Impl, render_items: bool, page: &mut Vec<Inline>) -> Result {
+    if imp.is_unsafe {
+        page.push(roman("unsafe "));
+    }
+
+    page.push(roman("impl"));
+    render_generics(cx, "", &imp.generics.params, 0, page)?;
+
+    page.push(roman(" "));
 
     if let Some(tr) = &imp.trait_ {
         if imp.negative {
             page.push(roman("!"));
         }
 
-        let path = if imp.synthetic {
-            tr.name.to_string()
-        } else {
-            cr.paths
-                .get(&tr.id)
-                .map(|tr| tr.path.clone())
-                .unwrap_or_else(|| {
-                    eprintln!("failed to find trait {}", tr.name);
-                    vec![tr.name.to_string()]
-                })
-                .join("::")
-        };
+        cx.reference(&tr.id);
+        if !imp.synthetic && !cx.cr.paths.contains_key(&tr.id) {
+            cx.warn(format!("failed to find trait {}", tr.name));
+        }
+        let path = cx.path_name(&tr.id, &tr.name);
         if let Some(args) = &tr.args {
-            render_generics_args(cr, &path, args, 0, page);
+            render_generics_args(cx, &path, args, 0, page)?;
         } else {
             page.push(bold(path));
         }
         page.push(roman(" for "));
     }
 
-    render_type(cr, &imp.for_, 0, page);
+    render_type(cx, &imp.for_, 0, page)?;
     page.push(line_break());
 
-    render_where(cr, &imp.generics.where_predicates, 0, page);
+    render_where(cx, &imp.generics.where_predicates, 0, page)?;
     page.push(line_break());
 
     if !render_items {
-        return;
+        return Ok(());
     }
 
-    for id in &imp.items {
-        let item = get(cr, id);
+    render_assoc_items(cx, &imp.items, page)?;
+    page.push(line_break());
+
+    Ok(())
+}
+
+/// Renders the docs and signatures of the items of an impl block.
+fn render_assoc_items(cx: &Context, items: &[Id], page: &mut Vec<Inline>) -> Result {
+    for id in items {
+        let item = get(cx, id)?;
 
         page.push(line_break());
         page.push(italic("  +-----+"));
         page.push(line_break());
-        page.push(line_break());
 
         if let Some(docs) = &item.docs {
-            page.push(roman("    "));
-            page.append(&mut markdown::to_roff(docs, 2));
+            render_nested_docs(cx, item, docs, 4, page);
+        } else {
+            page.push(line_break());
         }
 
-        match &item.inner {
-            ItemEnum::Function(_) => {
-                page.push(roman("  "));
-                render_fn(cr, id, 1, page);
-            }
-            ItemEnum::AssocConst { type_, default } => {
-                page.push(roman("  const "));
-                page.push(roman(item.name.as_ref().unwrap()));
-                page.push(roman(": "));
-                render_type(cr, type_, 1, page);
+        page.push(roman("  "));
+        render_assoc_signature(cx, id, 1, page)?;
 
-                if let Some(default) = default {
-                    page.push(roman(" = "));
-                    page.push(roman(default));
-                }
-            }
-            ItemEnum::AssocType {
-                generics,
-                bounds,
-                default,
-            } => {
-                page.push(roman("  type "));
-                render_generics(cr, item.name.as_ref().unwrap(), &generics.params, 0, page);
-
-                if !bounds.is_empty() {
-                    page.push(roman(": "));
-                    render_generics_bounds(cr, bounds, 0, page);
-                }
+        page.push(line_break());
+        page.push(line_break());
+    }
 
-                if let Some(default) = default {
-                    page.push(roman(" = "));
-                    render_type(cr, default, 1, page);
-                }
+    Ok(())
+}
 
-                page.push(line_break());
-                render_where(cr, &generics.where_predicates, 0, page);
+/// Renders the signature of a method, associated constant or associated type.
+fn render_assoc_signature(cx: &Context, id: &Id, depth: usize, page: &mut Vec<Inline>) -> Result {
+    let item = get(cx, id)?;
+    match &item.inner {
+        ItemEnum::Function(_) => render_fn(cx, id, depth, page)?,
+        ItemEnum::AssocConst { type_, default } => {
+            page.push(roman("const "));
+            page.push(roman(name(item)?));
+            page.push(roman(": "));
+            render_type(cx, type_, depth, page)?;
+
+            if let Some(default) = default {
+                page.push(roman(" = "));
+                page.push(roman(default));
             }
-            _ => panic!("unhandled item: {item:#?}"),
         }
+        ItemEnum::AssocType {
+            generics,
+            bounds,
+            default,
+        } => {
+            page.push(roman("type "));
+            let outer = depth.saturating_sub(1);
+            render_generics(cx, name(item)?, &generics.params, outer, page)?;
 
-        page.push(line_break());
-        page.push(line_break());
+            if !bounds.is_empty() {
+                page.push(roman(": "));
+                render_generics_bounds(cx, bounds, outer, page)?;
+            }
+
+            if let Some(default) = default {
+                page.push(roman(" = "));
+                render_type(cx, default, depth, page)?;
+            }
+
+            page.push(line_break());
+            render_where(cx, &generics.where_predicates, outer, page)?;
+        }
+        _ => return Err(unexpected(id, "associated item")),
     }
 
-    page.push(line_break());
+    Ok(())
 }
 
-fn render_variant(cr: &Crate, variant: &Variant, page: &mut Vec<Inline>) {
+fn render_variant(cx: &Context, variant: &Variant, page: &mut Vec<Inline>) -> Result {
     match &variant.kind {
         VariantKind::Plain => {}
         VariantKind::Tuple(fields) => {
@@ -722,12 +1395,12 @@ fn render_variant(cr: &Crate, variant: &Variant, page: &mut Vec<Inline>) {
                 let Item {
                     inner: ItemEnum::StructField(ty),
                     ..
-                } = get(cr, id)
+                } = get(cx, id)?
                 else {
-                    panic!("invalid variant type");
+                    return Err(unexpected(id, "struct field"));
                 };
 
-                render_type(cr, ty, depth, page);
+                render_type(cx, ty, depth, page)?;
             }
 
             if fields.len() < 3 {
@@ -742,54 +1415,44 @@ fn render_variant(cr: &Crate, variant: &Variant, page: &mut Vec<Inline>) {
             fields_stripped,
         } => {
             page.push(roman(" {"));
-            page.push(line_break());
-            page.push(roman("    "));
-
-            let sep = |page: &mut Vec<Inline>| {
-                page.push(roman(","));
-                page.push(line_break());
-                page.push(roman("    "));
-            };
 
             let mut first = true;
             for id in fields {
                 if !first {
-                    sep(page);
+                    page.push(roman(","));
                 }
 
+                let field = get(cx, id)?;
                 let Item {
-                    name,
+                    name: Some(name),
                     docs,
                     inner: ItemEnum::StructField(ty),
                     ..
-                } = get(cr, id)
+                } = field
                 else {
-                    panic!("invalid variant type");
+                    return Err(unexpected(id, "named struct field"));
                 };
 
                 if let Some(docs) = docs {
-                    if !first {
-                        page.push(line_break());
-                        page.push(roman("      "));
-                    } else {
-                        page.push(roman("  "));
-                    }
-                    page.append(&mut markdown::to_roff(docs, 3));
-                    page.push(roman("    "));
+                    render_nested_docs(cx, field, docs, 6, page);
+                } else {
+                    page.push(line_break());
                 }
 
                 first = false;
 
-                page.push(roman(name.as_ref().unwrap()));
+                page.push(roman("    "));
+                page.push(roman(name));
                 page.push(roman(": "));
-                render_type(cr, ty, 4, page);
+                render_type(cx, ty, 4, page)?;
             }
 
             if *fields_stripped {
                 if !first {
-                    sep(page);
+                    page.push(roman(","));
                 }
-                page.push(roman("/* hidden fields */"));
+                page.push(line_break());
+                page.push(roman("    /* hidden fields */"));
             }
 
             page.push(line_break());
@@ -801,59 +1464,63 @@ fn render_variant(cr: &Crate, variant: &Variant, page: &mut Vec<Inline>) {
         page.push(roman(" = "));
         page.push(roman(&*disc.expr));
     }
+
+    Ok(())
 }
 
-fn render_variants(cr: &Crate, variants: &[Id], stripped: bool, page: &mut Vec<Inline>) {
+fn render_variants(
+    cx: &Context,
+    variants: &[Id],
+    stripped: bool,
+    page: &mut Vec<Inline>,
+) -> Result {
     page.push(roman(" {"));
-    page.push(line_break());
-    page.push(roman("  "));
-
-    let sep = |page: &mut Vec<Inline>| {
-        page.extend_from_slice(&[roman(","), line_break(), line_break(), roman("  ")])
-    };
 
     let mut first = true;
     for id in variants {
-        let item = get(cr, id);
+        let item = get(cx, id)?;
         let Item {
-            name,
+            name: Some(name),
             docs,
             inner: ItemEnum::Variant(var),
             ..
         } = item
         else {
-            panic!("invalid variant")
+            return Err(unexpected(id, "named variant"));
         };
 
         if !first {
-            sep(page);
+            page.extend_from_slice(&[roman(","), line_break()]);
         }
         first = false;
 
         if let Some(docs) = docs {
-            page.push(roman("  "));
-            page.append(&mut markdown::to_roff(docs, 2));
-            page.push(roman("  "));
+            render_nested_docs(cx, item, docs, 4, page);
+        } else {
+            page.push(line_break());
         }
 
+        page.push(roman("  "));
         page.push(italic("variant "));
-        page.push(bold(name.as_ref().unwrap()));
-        render_variant(cr, var, page);
+        page.push(bold(name));
+        render_variant(cx, var, page)?;
     }
 
     if stripped {
         if !first {
-            sep(page);
+            page.extend_from_slice(&[roman(","), line_break()]);
         }
-        page.push(roman("/* hidden variants */"));
+        page.extend_from_slice(&[line_break(), roman("  /* hidden variants */")]);
     }
 
     page.push(line_break());
     page.push(roman("}"));
+
+    Ok(())
 }
 
-fn r#enum(cr: &Crate, id: &Id, page: &mut Roff) {
-    let en = get(cr, id);
+fn r#enum(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let en = get(cx, id)?;
     let ItemEnum::Enum(Enum {
         generics,
         variants_stripped,
@@ -861,10 +1528,10 @@ fn r#enum(cr: &Crate, id: &Id, page: &mut Roff) {
         impls,
     }) = &en.inner
     else {
-        panic!("expected struct")
+        return Err(unexpected(id, "enum"));
     };
 
-    let name = en.name.as_ref().unwrap();
+    let name = name(en)?;
     page.control("SH", ["SIGNATURE"]);
 
     let mut buf = Vec::new();
@@ -876,53 +1543,31 @@ fn r#enum(cr: &Crate, id: &Id, page: &mut Roff) {
         }
     }
 
-    render_generics(cr, name, &generics.params, 0, &mut buf);
-    render_where(cr, &generics.where_predicates, 0, &mut buf);
-    render_variants(cr, variants, *variants_stripped, &mut buf);
-    page.text(&buf[..]);
+    render_generics(cx, name, &generics.params, 0, &mut buf)?;
+    render_where(cx, &generics.where_predicates, 0, &mut buf)?;
+    render_variants(cx, variants, *variants_stripped, &mut buf)?;
+    render_code(page, buf);
 
-    if let Some(docs) = &en.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
-        }
-    }
+    render_docs(cx, en, page);
+
+    render_impls(cx, impls, page)?;
 
-    render_impls(cr, impls, page);
+    Ok(())
 }
 
-fn module(cr: &Crate, id: &Id, max_width: usize, page: &mut Roff) {
-    let module = get(cr, id);
+fn module(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let module = get(cx, id)?;
     let ItemEnum::Module(Module { items, .. }) = &module.inner else {
-        panic!("expected module")
+        return Err(unexpected(id, "module"));
     };
 
-    let name = module.name.as_ref().unwrap();
-    page.control("SH", ["NAME"]);
-    page.text([roman(name)]);
-
-    if let Some(docs) = &module.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
-        }
-    }
+    render_docs(cx, module, page);
 
-    render_items(cr, items, page, Some(max_width));
+    render_items(cx, items, page, Some(cx.opts.max_width))?;
 
     let mut first = true;
     for id in items {
-        let Item { docs, inner: ItemEnum::Import(import), .. } = get(cr, id) else { continue };
+        let Item { docs, inner: ItemEnum::Import(import), .. } = get(cx, id)? else { continue };
 
         if first {
             page.control("SH", ["RE-EXPORTS"]);
@@ -950,31 +1595,36 @@ fn module(cr: &Crate, id: &Id, max_width: usize, page: &mut Roff) {
                 .map(|s| s.0)
                 .unwrap_or(docs);
 
-            let remaining = max_width - width;
-
-            let end = floor_char_boundary(synopsis, if synopsis.len() >= remaining {
-                remaining.saturating_sub(3)
-            } else {
-                synopsis.len()
-            });
+            let remaining = cx.opts.max_width.saturating_sub(width);
 
-            page.text([
-                bold("// "),
-                roman(&synopsis[..end]), // TODO: parse this markdown
-                roman(if synopsis.len() >= remaining {
-                    "..."
+            // long paths can leave no room for any of it
+            if remaining > 3 {
+                let end = floor_char_boundary(synopsis, if synopsis.len() >= remaining {
+                    remaining - 3
                 } else {
-                    ""
-                })
-            ]);
+                    synopsis.len()
+                });
+
+                page.text([
+                    bold("// "),
+                    roman(&synopsis[..end]), // TODO: parse this markdown
+                    roman(if synopsis.len() >= remaining {
+                        "..."
+                    } else {
+                        ""
+                    })
+                ]);
+            }
         }
         
         page.text([line_break()]);
     }
+
+    Ok(())
 }
 
-fn trate(cr: &Crate, id: &Id, page: &mut Roff) {
-    let tr = get(cr, id);
+fn trate(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let tr = get(cx, id)?;
     let ItemEnum::Trait(Trait {
         is_auto,
         is_unsafe,
@@ -985,10 +1635,10 @@ fn trate(cr: &Crate, id: &Id, page: &mut Roff) {
         implementations,
     }) = &tr.inner
     else {
-        panic!("expected struct")
+        return Err(unexpected(id, "trait"));
     };
 
-    let name = tr.name.as_ref().unwrap();
+    let name = name(tr)?;
     page.control("SH", ["SIGNATURE"]);
 
     let mut buf = Vec::new();
@@ -1002,13 +1652,13 @@ fn trate(cr: &Crate, id: &Id, page: &mut Roff) {
     }
 
     buf.push(roman("trait "));
-    render_generics(cr, name, &generics.params, 0, &mut buf);
+    render_generics(cx, name, &generics.params, 0, &mut buf)?;
     if !bounds.is_empty() {
         buf.push(roman(": "));
     }
-    render_generics_bounds(cr, bounds, 0, &mut buf);
-    render_where(cr, &generics.where_predicates, 0, &mut buf);
-    page.text(&buf[..]);
+    render_generics_bounds(cx, bounds, 0, &mut buf)?;
+    render_where(cx, &generics.where_predicates, 0, &mut buf)?;
+    render_code(page, buf);
 
     page.control("SH", ["OBJECT SAFETY"]);
     if *is_object_safe {
@@ -1017,19 +1667,9 @@ fn trate(cr: &Crate, id: &Id, page: &mut Roff) {
         page.text([roman("This trait is "), bold("not"), roman(" object-safe.")]);
     }
 
-    if let Some(docs) = &tr.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
-        }
-    }
+    render_docs(cx, tr, page);
 
-    render_items(cr, items, page, None);
+    render_trait_items(cx, items, page)?;
 
     if !implementations.is_empty() {
         page.control("SH", ["IMPLEMENTORS"]);
@@ -1038,30 +1678,84 @@ fn trate(cr: &Crate, id: &Id, page: &mut Roff) {
             let Item {
                 inner: ItemEnum::Impl(imp),
                 ..
-            } = get(cr, id)
+            } = get(cx, id)?
             else {
-                panic!("invalid impl")
+                return Err(unexpected(id, "impl"));
             };
 
             let mut buf = Vec::new();
-            render_impl(cr, imp, false, &mut buf);
-            page.text(buf);
+            render_impl(cx, imp, false, &mut buf)?;
+            render_code(page, buf);
         }
     }
+
+    Ok(())
 }
 
-fn strukt(cr: &Crate, id: &Id, page: &mut Roff) {
-    let strukt = get(cr, id);
+/// Renders the items of a trait, in sections by what implementors have to
+/// provide themselves.
+fn render_trait_items(cx: &Context, items: &[Id], page: &mut Page) -> Result {
+    let (mut types, mut consts, mut required, mut provided) = (vec![], vec![], vec![], vec![]);
+    for id in items {
+        let item = get(cx, id)?;
+        match &item.inner {
+            ItemEnum::AssocType { default, .. } => types.push((item, default.is_some())),
+            ItemEnum::AssocConst { default, .. } => consts.push((item, default.is_some())),
+            ItemEnum::Function(func) if func.has_body => provided.push((item, false)),
+            ItemEnum::Function(_) => required.push((item, false)),
+            _ => return Err(unexpected(id, "associated item")),
+        }
+    }
+
+    for (header, items) in [
+        ("ASSOCIATED TYPES", types),
+        ("ASSOCIATED CONSTANTS", consts),
+        ("REQUIRED METHODS", required),
+        ("PROVIDED METHODS", provided),
+    ] {
+        if items.is_empty() {
+            continue;
+        }
+        page.control("SH", [header]);
+
+        for (i, (item, default)) in items.into_iter().enumerate() {
+            if i > 0 {
+                page.control("PP", []);
+            }
+
+            let mut buf = Vec::new();
+            render_assoc_signature(cx, &item.id, 0, &mut buf)?;
+            render_code(page, buf);
+
+            page.control("RS", ["2"]);
+            if default {
+                page.text([italic("Has a default, which implementors may override.")]);
+            }
+            if let Some(docs) = &item.docs {
+                if default {
+                    page.control("PP", []);
+                }
+                page.text(render_markdown(cx, item, docs));
+            }
+            page.control("RE", []);
+        }
+    }
+
+    Ok(())
+}
+
+fn strukt(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let strukt = get(cx, id)?;
     let ItemEnum::Struct(Struct {
         kind,
         generics,
         impls,
     }) = &strukt.inner
     else {
-        panic!("expected struct")
+        return Err(unexpected(id, "struct"));
     };
 
-    let name = strukt.name.as_ref().unwrap();
+    let name = name(strukt)?;
     page.control("SH", ["SIGNATURE"]);
 
     let mut buf = Vec::new();
@@ -1073,28 +1767,20 @@ fn strukt(cr: &Crate, id: &Id, page: &mut Roff) {
         }
     }
 
-    render_generics(cr, name, &generics.params, 0, &mut buf);
-    render_where(cr, &generics.where_predicates, 0, &mut buf);
-    render_fields(cr, kind, &mut buf);
-    page.text(&buf[..]);
+    render_generics(cx, name, &generics.params, 0, &mut buf)?;
+    render_where(cx, &generics.where_predicates, 0, &mut buf)?;
+    render_fields(cx, kind, &mut buf)?;
+    render_code(page, buf);
 
-    if let Some(docs) = &strukt.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
-        }
-    }
+    render_docs(cx, strukt, page);
+
+    render_impls(cx, impls, page)?;
 
-    render_impls(cr, impls, page);
+    Ok(())
 }
 
-fn onion(cr: &Crate, id: &Id, page: &mut Roff) {
-    let onion = get(cr, id);
+fn onion(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let onion = get(cx, id)?;
     let ItemEnum::Union(Union {
         generics,
         fields_stripped,
@@ -1102,10 +1788,10 @@ fn onion(cr: &Crate, id: &Id, page: &mut Roff) {
         impls,
     }) = &onion.inner
     else {
-        panic!("expected struct")
+        return Err(unexpected(id, "union"));
     };
 
-    let name = onion.name.as_ref().unwrap();
+    let name = name(onion)?;
     page.control("SH", ["SIGNATURE"]);
 
     let mut buf = Vec::new();
@@ -1117,256 +1803,203 @@ fn onion(cr: &Crate, id: &Id, page: &mut Roff) {
         }
     }
 
-    render_generics(cr, name, &generics.params, 0, &mut buf);
-    render_where(cr, &generics.where_predicates, 0, &mut buf);
+    render_generics(cx, name, &generics.params, 0, &mut buf)?;
+    render_where(cx, &generics.where_predicates, 0, &mut buf)?;
 
     buf.push(roman(" {"));
-    buf.push(line_break());
-    buf.push(roman("    "));
-
-    let sep = |buf: &mut Vec<Inline>| {
-        buf.push(roman(","));
-        buf.push(line_break());
-        buf.push(roman("    "));
-    };
 
     let mut first = true;
     for id in fields {
         if !first {
-            sep(&mut buf);
+            buf.push(roman(","));
         }
 
+        let field = get(cx, id)?;
         let Item {
-            name,
+            name: Some(name),
             docs,
             inner: ItemEnum::StructField(ty),
             ..
-        } = get(cr, id)
+        } = field
         else {
-            panic!("invalid variant type");
+            return Err(unexpected(id, "named struct field"));
         };
 
         if let Some(docs) = docs {
-            if !first {
-                buf.push(line_break());
-                buf.push(roman("      "));
-            } else {
-                buf.push(roman("  "));
-            }
-            buf.append(&mut markdown::to_roff(docs, 3));
-            buf.push(roman("    "));
+            render_nested_docs(cx, field, docs, 6, &mut buf);
+        } else {
+            buf.push(line_break());
         }
 
         first = false;
 
-        buf.push(roman(name.as_ref().unwrap()));
+        buf.push(roman("    "));
+        buf.push(roman(name));
         buf.push(roman(": "));
-        render_type(cr, ty, 4, &mut buf);
+        render_type(cx, ty, 4, &mut buf)?;
     }
 
     if *fields_stripped {
         if !first {
-            sep(&mut buf);
+            buf.push(roman(","));
         }
-        buf.push(roman("/* hidden fields */"));
+        buf.push(line_break());
+        buf.push(roman("    /* hidden fields */"));
     }
 
     buf.push(line_break());
     buf.push(roman("  }"));
 
-    page.text(&buf[..]);
+    render_code(page, buf);
 
-    if let Some(docs) = &onion.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
-        }
-    }
+    render_docs(cx, onion, page);
 
-    render_impls(cr, impls, page);
+    render_impls(cx, impls, page)?;
+
+    Ok(())
 }
 
-fn function(cr: &Crate, id: &Id, page: &mut Roff) {
-    let item = get(cr, id);
+fn function(cx: &Context, id: &Id, page: &mut Page) -> Result {
+    let item = get(cx, id)?;
     let ItemEnum::Function(_) = &item.inner else {
-        panic!("expected function")
+        return Err(unexpected(id, "function"));
     };
 
     page.control("SH", ["SIGNATURE"]);
-    let mut buf = Vec::new();
-    render_fn(cr, id, 0, &mut buf);
-    page.text(buf);
+    render_signature(cx, id, page)?;
 
-    if let Some(docs) = &item.docs {
-        if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-            page.control("SH", ["SYNOPSIS"]);
-            page.text(markdown::to_roff(synopsis, 0));
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(rest, 0));
-        } else {
-            page.control("SH", ["DESCRIPTION"]);
-            page.text(markdown::to_roff(docs, 0));
+    render_docs(cx, item, page);
+
+    Ok(())
+}
+
+/// Generates the page of an item, or nothing if it's an import. Pages that
+/// can't be generated fail on their own, without taking the run down.
+///
+/// Associated items are documented on pages of their own too, under the type
+/// or trait they belong to: their `owner`.
+pub fn gen(
+    cr: &Crate,
+    id: &Id,
+    owner: Option<&Id>,
+    opts: &Options,
+) -> Result<Option<Generated>, PageError> {
+    disambiguated(cr, opts, |cx| match owner {
+        Some(owner) => render_assoc_page(cx, owner, id).map(Some),
+        None => render_page(cx, id),
+    })
+    .map_err(|error| page_error(cr, id, owner, error))
+}
+
+/// Returns the name of the page of `id`, an associated item of `owner` if it
+/// has one, without generating the page.
+pub fn page_name(cr: &Crate, opts: &Options, id: &Id, owner: Option<&Id>) -> Option<String> {
+    let item = cr.index.get(id)?;
+    match owner {
+        Some(owner) => {
+            let cx = Context::new(cr, opts, HashMap::new());
+            let path = assoc_path(&cx, owner, id).ok()?;
+            Some(format!("{}:{path}", assoc_kind(item)?))
+        }
+        None => {
+            let path = match cr.paths.get(id) {
+                Some(summary) => summary.path.join("::"),
+                None => item.name.clone()?,
+            };
+            Some(format!("{}:{path}", kind(item)?))
         }
     }
 }
 
-pub fn gen(cr: &Crate, id: &Id, max_width: usize) -> Option<(String, Roff)> {
-    let item = get(cr, id);
+fn render_page(cx: &Context, id: &Id) -> Result<Option<Generated>> {
+    let item = get(cx, id)?;
     if matches!(item.inner, ItemEnum::Import(_)) {
-        return None;
+        return Ok(None);
     }
 
-    let path = cr
-        .paths
-        .get(id)
-        .map(|i| i.path.join("::"))
-        .unwrap_or_else(|| {
-            eprintln!("invalid ID: {}\n{:#?}", id.0, get(cr, id));
-            item.name.clone().unwrap()
-        });
+    let Some(typ) = kind(item) else {
+        return Err(unexpected(id, "documentable item"));
+    };
 
-    let typ = match &item.inner {
-        ItemEnum::Module(_) => "mod",
-        ItemEnum::Function(_) => "fn",
-        ItemEnum::Struct(_) => "struct",
-        ItemEnum::Enum(_) => "enum",
-        ItemEnum::Union(_) => "union",
-        ItemEnum::Trait(_) => "trait",
-        ItemEnum::TypeAlias(_) => "type",
-        ItemEnum::Constant(_) => "const",
-        ItemEnum::Static(_) => "static",
-        ItemEnum::Primitive(_) => "primitive",
-        ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => "macro",
-        _ => panic!("failed to catch {item:#?}"),
+    let path = match cx.cr.paths.get(id) {
+        Some(summary) => summary.path.join("::"),
+        None => {
+            let name = name(item)?;
+            cx.warn(format!("item {} ({typ} {name}) has no path", id.0));
+            name.to_string()
+        }
     };
     let full_name = format!("{typ}:{path}");
 
-    let mut page = Roff::new();
+    let mut page = Page::new();
     page.control("TH", [&full_name, "3r"]);
 
-    if let Some(dep) = &item.deprecation {
-        page.control("SH", ["DEPRECATED"]);
-        if let Some(since) = &dep.since {
-            page.text([italic("since "), italic(since), line_break()]);
-        }
-        if let Some(note) = &dep.note {
-            page.text([roman(note), line_break()]);
-        }
-    }
+    let summary = summary(cx, item).unwrap_or_else(|| format!("{typ} {path}"));
+    render_name(&full_name, &summary, &mut page);
+    render_deprecation(item, &mut page);
 
     match &item.inner {
         ItemEnum::Module(_) => {
-            module(cr, id, max_width, &mut page);
+            module(cx, id, &mut page)?;
         }
         ItemEnum::Union(_) => {
-            onion(cr, id, &mut page);
+            onion(cx, id, &mut page)?;
         }
         ItemEnum::Struct(_) => {
-            strukt(cr, id, &mut page);
+            strukt(cx, id, &mut page)?;
         }
         ItemEnum::Enum(_) => {
-            r#enum(cr, id, &mut page);
+            r#enum(cx, id, &mut page)?;
         }
         ItemEnum::Function(_) => {
-            function(cr, id, &mut page);
+            function(cx, id, &mut page)?;
         }
         ItemEnum::Macro(_) => {
-            page.control("SH", ["NAME"]);
-            page.text([roman("macro "), bold(item.name.as_ref().unwrap())]);
+            page.control("SH", ["SIGNATURE"]);
+            render_code(&mut page, vec![roman("macro "), bold(name(item)?)]);
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
-                }
-            }
+            render_docs(cx, item, &mut page);
         }
         ItemEnum::Trait(_) => {
-            trate(cr, id, &mut page);
+            trate(cx, id, &mut page)?;
         }
         ItemEnum::Primitive(pr) => {
-            page.control("SH", ["NAME"]);
-            page.text([roman("primitive "), bold(&pr.name)]);
+            page.control("SH", ["SIGNATURE"]);
+            render_code(&mut page, vec![roman("primitive "), bold(&pr.name)]);
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
-                }
-            }
+            render_docs(cx, item, &mut page);
 
-            render_impls(cr, &pr.impls, &mut page);
+            render_impls(cx, &pr.impls, &mut page)?;
         }
         ItemEnum::TypeAlias(alias) => {
             page.control("SH", ["SIGNATURE"]);
 
             let mut buf = vec![roman("type ")];
-            render_generics(
-                cr,
-                item.name.as_ref().unwrap(),
-                &alias.generics.params,
-                0,
-                &mut buf,
-            );
+            render_generics(cx, name(item)?, &alias.generics.params, 0, &mut buf)?;
             buf.push(roman(" = "));
-            render_type(cr, &alias.type_, 0, &mut buf);
+            render_type(cx, &alias.type_, 0, &mut buf)?;
 
             if !alias.generics.where_predicates.is_empty() {
                 buf.push(line_break());
-                render_where(cr, &alias.generics.where_predicates, 0, &mut buf);
+                render_where(cx, &alias.generics.where_predicates, 0, &mut buf)?;
             }
 
-            page.text(buf);
+            render_code(&mut page, buf);
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
-                }
-            }
+            render_docs(cx, item, &mut page);
         }
         ItemEnum::Constant(co) => {
             page.control("SH", ["SIGNATURE"]);
 
             let mut buf = vec![
                 roman("const "),
-                bold(item.name.as_ref().unwrap()),
+                bold(name(item)?),
                 roman(": "),
             ];
-            render_type(cr, &co.type_, 0, &mut buf);
-            page.text(buf);
+            render_type(cx, &co.type_, 0, &mut buf)?;
+            render_code(&mut page, buf);
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
-                }
-            }
+            render_docs(cx, item, &mut page);
         }
         ItemEnum::Static(st) => {
             page.control("SH", ["SIGNATURE"]);
@@ -1377,32 +2010,23 @@ pub fn gen(cr: &Crate, id: &Id, max_width: usize) -> Option<(String, Roff)> {
                 } else {
                     roman("static ")
                 },
-                bold(item.name.as_ref().unwrap()),
+                bold(name(item)?),
                 roman(": "),
             ];
-            render_type(cr, &st.type_, 0, &mut buf);
-            page.text(buf);
+            render_type(cx, &st.type_, 0, &mut buf)?;
+            render_code(&mut page, buf);
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
-                }
-            }
+            render_docs(cx, item, &mut page);
         }
         ItemEnum::ProcMacro(mac) => {
             page.control("SH", ["SIGNATURE"]);
-            let name = item.name.as_ref().unwrap();
-            match mac.kind {
-                MacroKind::Bang => page.text([roman("proc macro "), bold(name)]),
-                MacroKind::Attr => page.text([roman("#["), bold(name), roman("]")]),
-                MacroKind::Derive => page.text([roman("#[derive("), bold(name), roman("]")]),
+            let name = name(item)?;
+            let buf = match mac.kind {
+                MacroKind::Bang => vec![roman("proc macro "), bold(name)],
+                MacroKind::Attr => vec![roman("#["), bold(name), roman("]")],
+                MacroKind::Derive => vec![roman("#[derive("), bold(name), roman("]")],
             };
+            render_code(&mut page, buf);
 
             if !mac.helpers.is_empty() {
                 page.control("SH", ["ATTRS"]);
@@ -1411,25 +2035,449 @@ pub fn gen(cr: &Crate, id: &Id, max_width: usize) -> Option<(String, Roff)> {
                 }
             }
 
-            if let Some(docs) = &item.docs {
-                if let Some((synopsis, rest)) = docs.split_once("\n\n") {
-                    page.control("SH", ["SYNOPSIS"]);
-                    page.text(markdown::to_roff(synopsis, 0));
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(rest, 0));
-                } else {
-                    page.control("SH", ["DESCRIPTION"]);
-                    page.text(markdown::to_roff(docs, 0));
+            render_docs(cx, item, &mut page);
+        }
+
+        _ => return Err(unexpected(id, "documentable item")),
+    };
+
+    // impls mention the item itself all the time
+    cx.refs.borrow_mut().remove(&full_name);
+    render_examples(cx, &mut page);
+    render_notes(cx, &mut page);
+    render_links(cx, item, &mut page);
+
+    Ok(Some(Generated {
+        name: full_name,
+        page,
+        warnings: cx.warnings.take(),
+    }))
+}
+
+fn render_assoc_page(cx: &Context, owner: &Id, id: &Id) -> Result<Generated> {
+    let item = get(cx, id)?;
+    let owner_item = get(cx, owner)?;
+    let Some(typ) = assoc_kind(item) else {
+        return Err(unexpected(id, "associated item"));
+    };
+
+    let path = assoc_path(cx, owner, id)?;
+    let full_name = format!("{typ}:{path}");
+
+    let mut page = Page::new();
+    page.control("TH", [&full_name, "3r"]);
+
+    let summary = summary(cx, item).unwrap_or_else(|| format!("{typ} {path}"));
+    render_name(&full_name, &summary, &mut page);
+    render_deprecation(item, &mut page);
+
+    page.control("SH", ["SIGNATURE"]);
+    if let ItemEnum::Function(_) = &item.inner {
+        render_signature(cx, id, &mut page)?;
+    } else {
+        let mut buf = Vec::new();
+        render_assoc_signature(cx, id, 0, &mut buf)?;
+        render_code(&mut page, buf);
+    }
+
+    render_docs(cx, item, &mut page);
+
+    // where the item comes from: the impl block of a type, or a trait
+    cx.reference(owner);
+    match &owner_item.inner {
+        ItemEnum::Trait(_) => {
+            page.control("SH", ["TRAIT"]);
+            page.text([roman("trait "), bold(cx.path_name(owner, name(owner_item)?))]);
+        }
+        ItemEnum::Struct(Struct { impls, .. })
+        | ItemEnum::Enum(Enum { impls, .. })
+        | ItemEnum::Union(Union { impls, .. }) => {
+            for imp in impls {
+                let ItemEnum::Impl(imp) = &get(cx, imp)?.inner else {
+                    return Err(unexpected(imp, "impl"));
+                };
+                if imp.items.contains(id) {
+                    page.control("SH", ["IMPL"]);
+                    let mut buf = Vec::new();
+                    render_impl(cx, imp, false, &mut buf)?;
+                    render_code(&mut page, buf);
                 }
             }
         }
+        _ => return Err(unexpected(owner, "type or trait")),
+    }
+
+    render_examples(cx, &mut page);
+    render_notes(cx, &mut page);
+    render_links(cx, item, &mut page);
 
-        _ => panic!("failed to catch {item:#?}"),
+    Ok(Generated {
+        name: full_name,
+        page,
+        warnings: cx.warnings.take(),
+    })
+}
+
+/// Returns the path of an associated item, below the path of its owner. Items
+/// of the same name in several inherent impls are told apart by the generic
+/// arguments of their impls, like `Foo<u8>::new` and `Foo<u16>::new`.
+fn assoc_path(cx: &Context, owner: &Id, id: &Id) -> Result<String> {
+    let item = get(cx, id)?;
+    let owner_item = get(cx, owner)?;
+    let mut owner_path = match cx.cr.paths.get(owner) {
+        Some(summary) => summary.path.join("::"),
+        None => name(owner_item)?.to_string(),
     };
+    let name = name(item)?;
+
+    let namesake = assoc_items(cx.cr, owner).iter().any(|other| {
+        other != id && cx.cr.index.get(other).and_then(|o| o.name.as_deref()) == Some(name)
+    });
+    let impls = match &owner_item.inner {
+        ItemEnum::Struct(Struct { impls, .. })
+        | ItemEnum::Enum(Enum { impls, .. })
+        | ItemEnum::Union(Union { impls, .. }) if namesake => impls.as_slice(),
+        _ => &[],
+    };
+    for imp in impls {
+        if let ItemEnum::Impl(Impl {
+            items,
+            for_: Type::ResolvedPath(Path { args: Some(args), .. }),
+            ..
+        }) = &get(cx, imp)?.inner
+        {
+            if items.contains(id) {
+                // rendered apart, so that none of it ends up on the page
+                let cx = Context::new(cx.cr, cx.opts, HashMap::new());
+                let mut buf = Vec::new();
+                render_args(&cx, args, 0, &mut buf)?;
+                owner_path.push_str(&plain_text(&buf).replace(' ', ""));
+            }
+        }
+    }
+
+    Ok(format!("{owner_path}::{name}"))
+}
+
+/// Generates the index page of a crate, listing every item in it.
+pub fn index(cr: &Crate, opts: &Options) -> Result<Generated, PageError> {
+    disambiguated(cr, opts, render_index).map_err(|error| PageError {
+        kind: "crate",
+        ..page_error(cr, &cr.root, None, error)
+    })
+}
 
-    render_links(cr, item, &mut page);
+fn render_index(cx: &Context) -> Result<Generated> {
+    let (cr, opts) = (cx.cr, cx.opts);
+    let root = get(cx, &cr.root)?;
+    let name = name(root)?.to_string();
 
-    Some((full_name, page))
+    let mut page = Page::new();
+    page.control("TH", [&name, "3r"]);
+
+    let summary = summary(cx, root).unwrap_or_else(|| format!("the {name} crate"));
+    render_name(&name, &summary, &mut page);
+
+    page.control("SH", ["CRATE"]);
+    let mut buf = vec![roman("crate "), bold(&name)];
+    if let Some(version) = &cr.crate_version {
+        buf.push(roman(format!(" {version}")));
+    }
+    buf.push(line_break());
+    if let Some(features) = &opts.features {
+        buf.push(italic("features: "));
+        buf.push(roman(features));
+        buf.push(line_break());
+    }
+    if let Some(target) = &opts.target {
+        buf.push(italic("target: "));
+        buf.push(roman(target));
+        buf.push(line_break());
+    }
+    page.text(buf);
+
+    let mut items = Vec::new();
+    collect_items(cx, &cr.root, &mut items)?;
+    items.sort_by_cached_key(|id| cr.paths.get(id).map(|p| p.path.clone()));
+    render_items(cx, &items, &mut page, Some(opts.max_width))?;
+
+    cx.refs.borrow_mut().insert(format!("mod:{name}"));
+    render_links(cx, root, &mut page);
+
+    Ok(Generated {
+        name,
+        page,
+        warnings: cx.warnings.take(),
+    })
+}
+
+/// Renders a page, and renders it again if any names on it turned out to be
+/// ambiguous, with their paths qualified.
+fn disambiguated<T>(
+    cr: &Crate,
+    opts: &Options,
+    render: impl Fn(&Context) -> Result<T>,
+) -> Result<T> {
+    let cx = Context::new(cr, opts, HashMap::new());
+    let page = render(&cx)?;
+
+    let qualified = cx.ambiguous();
+    if qualified.is_empty() {
+        return Ok(page);
+    }
+
+    render(&Context::new(cr, opts, qualified))
+}
+
+/// Attaches the item a page is for to the error that kept it from being
+/// generated, as far as the item can be found.
+fn page_error(cr: &Crate, id: &Id, owner: Option<&Id>, error: Error) -> PageError {
+    let path = |id| {
+        cr.paths
+            .get(id)
+            .map(|summary| summary.path.join("::"))
+            .or_else(|| cr.index.get(id).and_then(|item| item.name.clone()))
+            .unwrap_or_else(|| id.0.clone())
+    };
+
+    let item = cr.index.get(id);
+    match owner {
+        Some(owner) => PageError {
+            path: format!("{}::{}", path(owner), path(id)),
+            kind: item.and_then(assoc_kind).unwrap_or("item"),
+            error,
+        },
+        None => PageError {
+            path: path(id),
+            kind: item.and_then(kind).unwrap_or("item"),
+            error,
+        },
+    }
+}
+
+/// Like [`kind`], for associated items.
+fn assoc_kind(item: &Item) -> Option<&'static str> {
+    match &item.inner {
+        ItemEnum::Function(_) => Some("method"),
+        ItemEnum::AssocConst { .. } => Some("assoc-const"),
+        ItemEnum::AssocType { .. } => Some("assoc-type"),
+        _ => None,
+    }
+}
+
+/// Returns the associated items of a type or trait that get pages of their
+/// own: everything in its inherent impls, or in the trait definition.
+pub fn assoc_items(cr: &Crate, id: &Id) -> Vec<Id> {
+    let Some(item) = cr.index.get(id) else {
+        return Vec::new();
+    };
+
+    let impls = match &item.inner {
+        ItemEnum::Trait(tr) => return tr.items.clone(),
+        ItemEnum::Struct(Struct { impls, .. })
+        | ItemEnum::Enum(Enum { impls, .. })
+        | ItemEnum::Union(Union { impls, .. }) => impls,
+        _ => return Vec::new(),
+    };
+
+    impls
+        .iter()
+        .filter_map(|id| match &cr.index.get(id)?.inner {
+            ItemEnum::Impl(imp) if imp.trait_.is_none() => Some(&imp.items),
+            _ => None,
+        })
+        .flatten()
+        .filter(|id| cr.index.contains_key(id))
+        .cloned()
+        .collect()
+}
+
+/// Finds the shortest path from the root of the crate to each of its items,
+/// through public modules and re-exports, so that items defined in private
+/// modules get the paths users can write. Re-exports under another name only
+/// count for items that can't be reached by their own.
+pub fn public_paths(cr: &Crate) -> HashMap<Id, Vec<String>> {
+    let mut paths = HashMap::new();
+    let mut renamed = HashMap::new();
+    let Some(root) = cr.index.get(&cr.root) else {
+        return paths;
+    };
+
+    let path = Vec::from_iter(root.name.clone());
+    paths.insert(cr.root.clone(), path.clone());
+
+    // modules are visited in order of the length of their paths
+    let mut modules = VecDeque::from([(&cr.root, path)]);
+    let mut visited = HashSet::new();
+    while let Some((id, path)) = modules.pop_front() {
+        let Some(ItemEnum::Module(module)) = cr.index.get(id).map(|item| &item.inner) else {
+            continue;
+        };
+        if !visited.insert(id) {
+            continue;
+        }
+
+        for item in module.items.iter().filter_map(|id| cr.index.get(id)) {
+            let (target, name) = match &item.inner {
+                // `pub use inner::*` puts the items of `inner` next to the others
+                ItemEnum::Import(import) if import.glob => {
+                    if let Some(target) = &import.id {
+                        modules.push_front((target, path.clone()));
+                    }
+                    continue;
+                }
+                ItemEnum::Import(import) => match &import.id {
+                    Some(target) => (target, &import.name),
+                    None => continue,
+                },
+                ItemEnum::Module(module) if module.is_stripped => continue,
+                _ => match &item.name {
+                    Some(name) => (&item.id, name),
+                    None => continue,
+                },
+            };
+
+            let mut path = path.clone();
+            path.push(name.clone());
+            let target_item = cr.index.get(target);
+            let own = target_item.and_then(|item| item.name.as_ref());
+            let found = if own.is_some_and(|own| own != name) {
+                &mut renamed
+            } else {
+                &mut paths
+            };
+            found.entry(target.clone()).or_insert_with(|| path.clone());
+
+            if let Some(ItemEnum::Module(_)) = target_item.map(|item| &item.inner) {
+                modules.push_back((target, path));
+            }
+        }
+    }
+
+    for (id, path) in renamed {
+        paths.entry(id).or_insert(path);
+    }
+    paths
+}
+
+/// Returns the kind of an item as it appears in page names, if it gets a page.
+fn kind(item: &Item) -> Option<&'static str> {
+    Some(match &item.inner {
+        ItemEnum::Module(_) => "mod",
+        ItemEnum::Function(_) => "fn",
+        ItemEnum::Struct(_) => "struct",
+        ItemEnum::Enum(_) => "enum",
+        ItemEnum::Union(_) => "union",
+        ItemEnum::Trait(_) => "trait",
+        ItemEnum::TypeAlias(_) => "type",
+        ItemEnum::Constant(_) => "const",
+        ItemEnum::Static(_) => "static",
+        ItemEnum::Primitive(_) => "primitive",
+        ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => "macro",
+        _ => return None,
+    })
+}
+
+/// Returns the short names an item can be found by: every suffix of its path,
+/// like `Vec` and `vec::Vec`, and its `#[doc(alias)]`es. Associated items
+/// always keep their owner in the path, like `Vec::push`.
+pub fn aliases(cr: &Crate, id: &Id, owner: Option<&Id>) -> Vec<String> {
+    let mut aliases = Vec::new();
+    match owner {
+        Some(owner) => {
+            let name = cr.index.get(id).and_then(|item| item.name.as_ref());
+            if let (Some(summary), Some(name)) = (cr.paths.get(owner), name) {
+                for i in 0..summary.path.len() {
+                    aliases.push(format!("{}::{name}", summary.path[i..].join("::")));
+                }
+            }
+        }
+        None => {
+            if let Some(summary) = cr.paths.get(id) {
+                for i in 0..summary.path.len() {
+                    aliases.push(summary.path[i..].join("::"));
+                }
+            }
+        }
+    }
+
+    for attr in cr.index.get(id).into_iter().flat_map(|item| &item.attrs) {
+        // either `#[doc(alias = "a")]` or `#[doc(alias("a", "b"))]`
+        let Some(rest) = attr
+            .strip_prefix("#[doc(")
+            .and_then(|attr| attr.split_once("alias"))
+            .map(|(_, rest)| rest.split(')').next().unwrap_or(rest))
+        else {
+            continue;
+        };
+
+        aliases.extend(rest.split('"').skip(1).step_by(2).map(String::from));
+    }
+
+    // they become file names
+    aliases.retain(|alias| !alias.is_empty() && !alias.contains(['/', '.']));
+    aliases
+}
+
+/// Generates the page for a short name that could refer to several pages.
+pub fn disambiguation(name: &str, candidates: &BTreeSet<String>) -> Page {
+    let mut page = Page::new();
+    page.control("TH", [name, "3r"]);
+    render_name(name, "ambiguous name", &mut page);
+
+    page.control("SH", ["DESCRIPTION"]);
+    page.text([bold(name), roman(" could refer to any of the pages below.")]);
+
+    page.control("SH", ["SEE ALSO"]);
+    for candidate in candidates {
+        page.text([bold(candidate), roman("(3r)"), line_break()]);
+    }
+
+    page
+}
+
+/// Collects every item in a module and its submodules, recursively.
+fn collect_items(cx: &Context, id: &Id, items: &mut Vec<Id>) -> Result {
+    let ItemEnum::Module(Module { items: children, .. }) = &get(cx, id)?.inner else {
+        return Ok(());
+    };
+
+    for id in children {
+        if !cx.cr.index.contains_key(id) {
+            continue;
+        }
+
+        items.push(id.clone());
+        collect_items(cx, id, items)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the synopsis of an item's docs as plain text.
+fn summary(cx: &Context, item: &Item) -> Option<String> {
+    let docs = item.docs.as_deref()?;
+    let synopsis = docs.split("\n\n").next().unwrap_or(docs);
+    Some(markdown::to_plain(synopsis, &DocLinks { cx, item })).filter(|s| !s.is_empty())
+}
+
+fn render_deprecation(item: &Item, page: &mut Page) {
+    if let Some(dep) = &item.deprecation {
+        page.control("SH", ["DEPRECATED"]);
+        if let Some(since) = &dep.since {
+            page.text([italic("since "), italic(since), line_break()]);
+        }
+        if let Some(note) = &dep.note {
+            page.text([roman(note), line_break()]);
+        }
+    }
+}
+
+/// Renders `name - summary`, which is what mandb indexes for whatis(1) and
+/// apropos(1).
+fn render_name(name: &str, summary: &str, page: &mut Page) {
+    page.control("SH", ["NAME"]);
+    page.text([roman(format!("{name} - {summary}"))]);
 }
 
 fn floor_char_boundary(s: &str, index: usize) -> usize {
@@ -1450,12 +2498,12 @@ fn floor_char_boundary(s: &str, index: usize) -> usize {
 
 macro_rules! render_item_kinds {
     (
-        $cr:expr, $items:expr, $page:expr, $max_width:expr;
+        $cx:expr, $items:expr, $page:expr, $max_width:expr;
         $( $header:literal : $name:ident $kind:ident );+
     $(;)? ) => {$(
         let mut first = true;
         for id in $items {
-            let item = get($cr, id);
+            let item = get($cx, id)?;
             #[allow(unused)]
             if let ItemEnum::$kind(inner) = &item.inner {
                 if first {
@@ -1463,19 +2511,21 @@ macro_rules! render_item_kinds {
                 }
                 first = false;
 
-                let path = $cr.paths.get(id)
-                    .map(|i| i.path.join("::"))
-                    .unwrap_or_else(|| {
-                        eprintln!(
+                let path = match $cx.cr.paths.get(id) {
+                    Some(summary) => summary.path.join("::"),
+                    None => {
+                        let name = name(item)?;
+                        $cx.warn(format!(
                             concat!(
                                 "no path for ",
                                 stringify!($name),
                                 " {}",
                             ),
-                            item.name.as_ref().unwrap(),
-                        );
-                        item.name.clone().unwrap()
-                    });
+                            name,
+                        ));
+                        name.to_string()
+                    }
+                };
 
                 if $max_width.is_some() {
                     $page.text([
@@ -1492,27 +2542,30 @@ macro_rules! render_item_kinds {
                             .unwrap_or(docs);
 
                         let width = stringify!($name).len() + path.len() + 5;
-                        let remaining = max_width - width;
-
-                        let end = floor_char_boundary(synopsis, if synopsis.len() >= remaining {
-                            remaining.saturating_sub(3)
-                        } else {
-                            synopsis.len()
-                        });
-
-                        $page.text([
-                            bold("// "),
-                            roman(&synopsis[..end]), // TODO: parse this markdown
-                            roman(if synopsis.len() >= remaining {
-                                "..."
+                        let remaining = max_width.saturating_sub(width);
+
+                        // long paths can leave no room for any of it
+                        if remaining > 3 {
+                            let end = floor_char_boundary(synopsis, if synopsis.len() >= remaining {
+                                remaining - 3
                             } else {
-                                ""
-                            })
-                        ]);
+                                synopsis.len()
+                            });
+
+                            $page.text([
+                                bold("// "),
+                                roman(&synopsis[..end]), // TODO: parse this markdown
+                                roman(if synopsis.len() >= remaining {
+                                    "..."
+                                } else {
+                                    ""
+                                })
+                            ]);
+                        }
                     } else {
-                        let mut buf = markdown::to_roff(docs, 1);
-                        buf.insert(0, roman("  "));
-                        $page.text(buf);
+                        $page.control("RS", ["2"]);
+                        $page.text(render_markdown($cx, item, docs));
+                        $page.control("RE", []);
                     }
                 }
 
@@ -0,0 +1,42 @@
+use rustdoc_types::Id;
+
+use std::fmt;
+
+/// Why part of a page couldn't be rendered.
+#[derive(Debug)]
+pub enum Error {
+    /// An ID that isn't in the crate's index.
+    MissingItem(Id),
+    /// An item that isn't of the kind where it was found.
+    UnexpectedItem { id: Id, expected: &'static str },
+    /// An item without the name it should have.
+    MissingName(Id),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingItem(id) => write!(f, "item {} isn't in the crate", id.0),
+            Error::UnexpectedItem { id, expected } => {
+                write!(f, "expected item {} to be a {expected}", id.0)
+            }
+            Error::MissingName(id) => write!(f, "item {} has no name", id.0),
+        }
+    }
+}
+
+/// An [`Error`] that kept a page from being generated.
+#[derive(Debug)]
+pub struct PageError {
+    /// The path of the item the page documents.
+    pub path: String,
+    /// The kind of the item, as in page names.
+    pub kind: &'static str,
+    pub error: Error,
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to generate {}:{}: {}", self.kind, self.path, self.error)
+    }
+}
@@ -0,0 +1,306 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+mod man;
+mod markdown;
+mod mdoc;
+mod text;
+
+/// A part of a text line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Inline {
+    Roman(String),
+    Italic(String),
+    Bold(String),
+    LineBreak,
+    /// Starts a hyperlink to a URL; the text up to [`Inline::LinkEnd`] is what
+    /// gets linked.
+    LinkStart(String),
+    LinkEnd,
+    /// Starts text in a color, up to [`Inline::ColorEnd`]. Only roff has
+    /// colors; other formats leave them out.
+    ColorStart(Color),
+    ColorEnd,
+    /// A man(7) macro amid text, like `RS` to indent what follows.
+    /// [`Page::text`] moves these onto control lines of their own.
+    Control(String, Vec<String>),
+    /// A table, which [`Page::text`] also moves onto lines of its own.
+    Table(Table),
+}
+
+/// The colors of groff(7) that terminals can show, for [`Inline::ColorStart`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Blue,
+    Green,
+    Magenta,
+}
+
+impl Color {
+    /// The name of the color in a `\m` escape.
+    fn name(self) -> &'static str {
+        match self {
+            Color::Blue => "blue",
+            Color::Green => "green",
+            Color::Magenta => "magenta",
+        }
+    }
+}
+
+/// How the cells of a table column are aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// The key of the alignment in a tbl(1) format.
+    fn tbl(self) -> char {
+        match self {
+            Align::Left => 'l',
+            Align::Center => 'c',
+            Align::Right => 'r',
+        }
+    }
+}
+
+/// A table with a header row, written with tbl(1) where roff can.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Table {
+    pub align: Vec<Align>,
+    /// The header, then the other rows. Cells hold no line breaks, links or
+    /// tabs, so that they fit on a tbl(1) line.
+    pub rows: Vec<Vec<Vec<Inline>>>,
+}
+
+impl Table {
+    /// Returns the tbl(1) format of the table: a bold header over the rest.
+    fn tbl_format(&self) -> [String; 2] {
+        let keys = |font| {
+            let keys: Vec<_> = self.align.iter().map(|a| format!("{}{font}", a.tbl())).collect();
+            keys.join(" ")
+        };
+        [keys("B"), keys("") + "."]
+    }
+
+    /// Returns whether tbl(1) would take a cell for a rule or a span instead
+    /// of its text, which it does with `_`, `=`, `\_` or `\^` on their own.
+    fn tbl_special(cell: &[Inline]) -> bool {
+        let mut text = String::new();
+        for inline in cell {
+            match inline {
+                Inline::Roman(s) => text.push_str(s),
+                _ => return false,
+            }
+        }
+        matches!(text.as_str(), "_" | "=" | r"\_" | r"\^")
+    }
+}
+
+/// The signature of a function, which mdoc(7) writes with its function macros
+/// (`Fo`, `Fa` and `Fc`). Other formats write it as code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    /// The signature as code, laid out line by line.
+    pub code: Vec<Inline>,
+    /// What comes before the name, like `const unsafe fn`.
+    pub qualifiers: String,
+    /// The name, along with any generic parameters.
+    pub name: String,
+    /// The arguments, like `f: u8`.
+    pub args: Vec<String>,
+    /// The return type, like `-> u8`, if any.
+    pub output: String,
+    /// The where clause, if any.
+    pub bounds: String,
+}
+
+pub fn roman(s: impl Into<String>) -> Inline {
+    Inline::Roman(s.into())
+}
+
+pub fn italic(s: impl Into<String>) -> Inline {
+    Inline::Italic(s.into())
+}
+
+pub fn bold(s: impl Into<String>) -> Inline {
+    Inline::Bold(s.into())
+}
+
+pub fn line_break() -> Inline {
+    Inline::LineBreak
+}
+
+pub fn link_start(url: impl Into<String>) -> Inline {
+    Inline::LinkStart(url.into())
+}
+
+pub fn link_end() -> Inline {
+    Inline::LinkEnd
+}
+
+pub fn color_start(color: Color) -> Inline {
+    Inline::ColorStart(color)
+}
+
+pub fn color_end() -> Inline {
+    Inline::ColorEnd
+}
+
+pub fn control<'a>(name: &str, args: impl IntoIterator<Item = &'a str>) -> Inline {
+    Inline::Control(name.to_string(), args.into_iter().map(String::from).collect())
+}
+
+pub fn table(align: Vec<Align>, rows: Vec<Vec<Vec<Inline>>>) -> Inline {
+    Inline::Table(Table { align, rows })
+}
+
+#[derive(Debug, Clone, Hash)]
+enum Line {
+    /// A man(7) macro, e.g. `TH` or `SH`. Other formats translate these.
+    /// `EX` can name the language of the code, which only Markdown keeps.
+    Control { name: String, args: Vec<String> },
+    Text(Vec<Inline>),
+    Table(Table),
+    Signature(Signature),
+}
+
+/// A manpage, independent of the format it will be written in.
+///
+/// Mirrors the API of `roff::Roff`: pages are built out of man(7) control
+/// lines and text lines, which each [`Format`] then translates.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct Page {
+    lines: Vec<Line>,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn control<'a>(
+        &mut self,
+        name: impl Into<String>,
+        args: impl IntoIterator<Item = &'a str>,
+    ) -> &mut Self {
+        self.lines.push(Line::Control {
+            name: name.into(),
+            args: args.into_iter().map(String::from).collect(),
+        });
+        self
+    }
+
+    pub fn text(&mut self, inlines: impl Into<Vec<Inline>>) -> &mut Self {
+        let inlines = inlines.into();
+        // empty lines are kept, as they separate paragraphs
+        if inlines.is_empty() {
+            self.lines.push(Line::Text(inlines));
+            return self;
+        }
+
+        let mut buf = Vec::new();
+        for inline in inlines {
+            let line = match inline {
+                Inline::Control(name, args) => Line::Control { name, args },
+                Inline::Table(table) => Line::Table(table),
+                inline => {
+                    buf.push(inline);
+                    continue;
+                }
+            };
+            if !buf.is_empty() {
+                self.lines.push(Line::Text(std::mem::take(&mut buf)));
+            }
+            self.lines.push(line);
+        }
+        if !buf.is_empty() {
+            self.lines.push(Line::Text(buf));
+        }
+        self
+    }
+
+    pub fn signature(&mut self, signature: Signature) -> &mut Self {
+        self.lines.push(Line::Signature(signature));
+        self
+    }
+
+    /// Returns the page with its signatures written out as code, for the
+    /// formats without macros for them.
+    fn code(&self) -> Page {
+        let mut page = Page::new();
+        for line in &self.lines {
+            match line {
+                Line::Signature(signature) => {
+                    page.control("EX", ["rust"]);
+                    page.text(signature.code.clone());
+                    page.control("EE", []);
+                }
+                line => page.lines.push(line.clone()),
+            }
+        }
+        page
+    }
+
+    /// Whether the page has any tables, which roff needs tbl(1) for.
+    fn has_tables(&self) -> bool {
+        self.lines.iter().any(|line| matches!(line, Line::Table(_)))
+    }
+
+    pub fn write(&self, format: Format, w: &mut dyn Write) -> io::Result<()> {
+        match format {
+            Format::Man => man::write(&self.code(), w),
+            Format::Mdoc => mdoc::write(self, w),
+            Format::Text => text::write(&self.code(), w),
+            Format::Markdown => markdown::write(&self.code(), w),
+        }
+    }
+}
+
+/// The output format of the generated pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// man(7), the classic manpage macros.
+    Man,
+    /// mdoc(7), the semantic macros preferred by BSD and mandoc.
+    Mdoc,
+    /// Plain text, for reading without `man`.
+    Text,
+    /// CommonMark, for editors and wikis.
+    Markdown,
+}
+
+impl Format {
+    /// The extension of the written files, without any compression suffix.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Man | Format::Mdoc => "3r",
+            Format::Text => "txt",
+            Format::Markdown => "md",
+        }
+    }
+
+    /// Whether the written files should be gzipped. Only `man` knows how to
+    /// read compressed pages.
+    pub fn compressed(self) -> bool {
+        matches!(self, Format::Man | Format::Mdoc)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "man" => Ok(Self::Man),
+            "mdoc" => Ok(Self::Mdoc),
+            "text" => Ok(Self::Text),
+            "markdown" => Ok(Self::Markdown),
+            _ => Err(format!(
+                "unknown format `{s}` (expected man, mdoc, text or markdown)"
+            )),
+        }
+    }
+}
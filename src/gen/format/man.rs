@@ -0,0 +1,154 @@
+use std::io::{self, Write};
+
+use roff::Roff;
+
+use super::{Inline, Line, Page, Table};
+
+/// Stands in for the backslash of the escapes the roff crate can't write, as it
+/// escapes any backslash it's given.
+const ESCAPE: char = '\u{E000}';
+
+pub fn write(page: &Page, w: &mut dyn Write) -> io::Result<()> {
+    let mut roff = Roff::new();
+    for line in &page.lines {
+        match line {
+            Line::Control { name, args } => {
+                // roff only quotes arguments with spaces, but empty ones (like
+                // the tag of `IP`) would disappear without quotes too
+                let args = args.iter().map(|a| if a.is_empty() { "\"\"" } else { a });
+                // only Markdown has a use for the language of code
+                let args = args.filter(|_| name != "EX");
+                roff.control(name, args);
+            }
+            Line::Text(inlines) => text(&mut roff, inlines),
+            Line::Table(table) => tbl(&mut roff, table),
+            Line::Signature(_) => unreachable!("written as code by Page::write"),
+        }
+    }
+
+    // man(1) runs tbl(1) on the pages that ask for it on their first line
+    if page.has_tables() {
+        writeln!(w, r#"'\" t"#)?;
+    }
+
+    w.write_all(roff.render().replace(ESCAPE, "\\").as_bytes())
+}
+
+/// Writes a table as tbl(1) input, with tabs between the cells.
+fn tbl(roff: &mut Roff, table: &Table) {
+    roff.control("TS", []);
+    for format in table.tbl_format() {
+        roff.text([roff::roman(format)]);
+    }
+
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut line = Vec::new();
+        for (j, cell) in row.iter().enumerate() {
+            if j > 0 {
+                line.push(roff::roman("\t"));
+            }
+            if Table::tbl_special(cell) {
+                line.push(roff::roman(format!("{ESCAPE}&")));
+            }
+            line.extend(cell.iter().map(inline));
+        }
+        roff.text(line);
+
+        // a rule under the header
+        if i == 0 {
+            roff.text([roff::roman("_")]);
+        }
+    }
+    roff.control("TE", []);
+}
+
+/// Writes a text line, splitting it around the `UR`/`UE` (or `MT`/`ME`) macros
+/// of any links.
+fn text(roff: &mut Roff, inlines: &[Inline]) {
+    // empty lines are kept, as they separate paragraphs
+    if inlines.is_empty() {
+        roff.text([]);
+        return;
+    }
+
+    let mut buf = Vec::new();
+    let mut end = "UE";
+    let mut inlines = inlines.iter();
+    while let Some(i) = inlines.next() {
+        match i {
+            Inline::LinkStart(url) => {
+                // the macro line already separates the link from the text
+                if let Some(Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s)) = buf.last_mut()
+                {
+                    s.truncate(s.trim_end().len());
+                }
+                flush(roff, &mut buf);
+                let address = url.strip_prefix("mailto:");
+                if let Some(address) = address {
+                    roff.control("MT", [address]);
+                    end = "ME";
+                } else {
+                    roff.control("UR", [url.as_str()]);
+                    end = "UE";
+                }
+
+                // without any text, the macros print the URL themselves
+                let len = inlines
+                    .as_slice()
+                    .iter()
+                    .position(|i| *i == Inline::LinkEnd)
+                    .unwrap_or(0);
+                let label: String = inlines.as_slice()[..len]
+                    .iter()
+                    .map(|i| match i {
+                        Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => s.as_str(),
+                        _ => " ",
+                    })
+                    .collect();
+                if len > 0 && label.trim() == address.unwrap_or(url) {
+                    inlines.nth(len - 1);
+                }
+            }
+            Inline::LinkEnd => {
+                flush(roff, &mut buf);
+
+                // punctuation right after the link goes on the same line, or
+                // it would be separated by a space
+                let mut punct = None;
+                if let Some(Inline::Roman(s)) = inlines.as_slice().first() {
+                    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+                    punct = Some(&s[..end]).filter(|p| !p.is_empty());
+                    let rest = s[end..].trim_start();
+                    if !rest.is_empty() {
+                        buf.push(Inline::Roman(rest.to_string()));
+                    }
+                    inlines.next();
+                }
+                roff.control(end, punct);
+            }
+            i => buf.push(i.clone()),
+        }
+    }
+
+    flush(roff, &mut buf);
+}
+
+/// Writes the inlines collected so far as a text line, if there are any.
+fn flush(roff: &mut Roff, buf: &mut Vec<Inline>) {
+    if !buf.is_empty() {
+        roff.text(buf.drain(..).map(|i| inline(&i)).collect::<Vec<_>>());
+    }
+}
+
+fn inline(inline: &Inline) -> roff::Inline {
+    match inline {
+        Inline::Roman(s) => roff::roman(s),
+        Inline::Italic(s) => roff::italic(s),
+        Inline::Bold(s) => roff::bold(s),
+        Inline::LineBreak => roff::line_break(),
+        Inline::ColorStart(color) => roff::roman(format!("{ESCAPE}m[{}]", color.name())),
+        Inline::ColorEnd => roff::roman(format!("{ESCAPE}m[]")),
+        Inline::LinkStart(_) | Inline::LinkEnd => unreachable!("handled by text()"),
+        Inline::Control(..) | Inline::Table(_) => unreachable!("split off by Page::text"),
+    }
+}
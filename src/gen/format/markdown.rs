@@ -0,0 +1,310 @@
+use std::io::{self, Write};
+
+use super::{Align, Inline, Line, Page, Table};
+
+/// Writes a page as CommonMark: sections become headings, text lines become
+/// paragraphs, line breaks become hard breaks, and code (`EX`) becomes fenced
+/// code blocks. Tables are written as GFM tables, which most renderers
+/// understand too.
+pub fn write(page: &Page, w: &mut dyn Write) -> io::Result<()> {
+    let mut in_paragraph = false;
+    let mut at_line_start = true;
+    // the line ending owed before the next text: soft, or a hard break
+    let mut pending = None;
+    let mut link = None;
+    // the language and lines of the code being written
+    let mut code: Option<(&str, Code)> = None;
+    for line in &page.lines {
+        match line {
+            // nothing else can go in code, which only `EE` ends
+            Line::Control { name, .. } if code.is_some() => {
+                if name == "EE" {
+                    let (lang, code) = code.take().unwrap();
+                    fence(lang, &code.finish(), w)?;
+                }
+            }
+            Line::Text(inlines) if code.is_some() => code.as_mut().unwrap().1.text(inlines),
+            Line::Control { name, args } => {
+                if in_paragraph {
+                    writeln!(w, "\n")?;
+                    in_paragraph = false;
+                }
+                at_line_start = true;
+                pending = None;
+
+                match (name.as_str(), args.as_slice()) {
+                    ("TH", [title, sect, ..]) => writeln!(w, "# {}({sect})\n", escape(title))?,
+                    ("SH", [name]) => writeln!(w, "## {}\n", escape(name))?,
+                    ("SS", [name]) => writeln!(w, "### {}\n", escape(name))?,
+                    ("EX", args) => {
+                        let lang = args.first().map_or("", String::as_str);
+                        code = Some((lang, Code::default()));
+                    }
+                    // list items start with their bullet or number, which are
+                    // written as Markdown's own
+                    ("IP", [tag, ..]) if !tag.is_empty() => {
+                        write!(w, "{tag} ")?;
+                        in_paragraph = true;
+                        at_line_start = false;
+                        // the item's text goes right after it
+                        pending = Some("");
+                    }
+                    // anything else (`PP`, `RS`, ...) just ends the paragraph
+                    _ => {}
+                }
+            }
+            Line::Text(inlines) => {
+                if in_paragraph && pending.is_none() {
+                    pending = Some("\n");
+                    at_line_start = true;
+                }
+
+                for inline in inlines {
+                    let s = match inline {
+                        Inline::LineBreak => {
+                            // like `.br`, breaks never stack up into blank lines
+                            if in_paragraph {
+                                pending = Some("\\\n");
+                            }
+                            at_line_start = true;
+                            continue;
+                        }
+                        Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => s,
+                        Inline::LinkStart(url) => {
+                            if let Some(ending) = pending.take() {
+                                write!(w, "{ending}")?;
+                            }
+                            write!(w, "[")?;
+                            link = Some(url);
+                            in_paragraph = true;
+                            at_line_start = false;
+                            continue;
+                        }
+                        Inline::LinkEnd => {
+                            if let Some(url) = link.take() {
+                                write!(w, "](<{url}>)")?;
+                            }
+                            continue;
+                        }
+                        Inline::ColorStart(_) | Inline::ColorEnd => continue,
+                        Inline::Control(..) | Inline::Table(_) => {
+                            unreachable!("split off by Page::text")
+                        }
+                    };
+
+                    if s.is_empty() {
+                        continue;
+                    }
+
+                    if let Some(ending) = pending.take() {
+                        write!(w, "{ending}")?;
+                    }
+
+                    let blank = s.trim().is_empty();
+                    let mut s = escape(&s.replace('\n', " "));
+                    if at_line_start {
+                        // leading whitespace would be stripped (or start a code
+                        // block), and leading markers would start a list or heading
+                        let trimmed = s.trim_start_matches(' ');
+                        let marker = if trimmed.starts_with(['-', '+', '=']) {
+                            "\\"
+                        } else {
+                            ""
+                        };
+                        s = "&nbsp;".repeat(s.len() - trimmed.len()) + marker + trimmed;
+                    }
+
+                    let delim = match inline {
+                        Inline::Italic(_) => "*",
+                        Inline::Bold(_) => "**",
+                        _ => "",
+                    };
+
+                    // emphasis can't start or end with whitespace
+                    let core = s.trim();
+                    if delim.is_empty() || core.is_empty() {
+                        write!(w, "{s}")?;
+                    } else {
+                        let start = s.len() - s.trim_start().len();
+                        let end = start + core.len();
+                        write!(w, "{}{delim}{core}{delim}{}", &s[..start], &s[end..])?;
+                    }
+
+                    in_paragraph = true;
+                    at_line_start = at_line_start && blank;
+                }
+            }
+            Line::Signature(_) => unreachable!("written as code by Page::write"),
+            Line::Table(t) => {
+                if in_paragraph {
+                    writeln!(w, "\n")?;
+                    in_paragraph = false;
+                }
+                at_line_start = true;
+                pending = None;
+
+                table(t, w)?;
+            }
+        }
+    }
+
+    if in_paragraph {
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// The lines of code, laid out like roff does without filling: every text
+/// line and line break starts a line, but breaks don't stack up into blank
+/// lines.
+#[derive(Default)]
+struct Code {
+    lines: Vec<String>,
+    /// Whether the last line is still being written.
+    open: bool,
+}
+
+impl Code {
+    fn text(&mut self, inlines: &[Inline]) {
+        // like an empty line of roff input, it's a blank line
+        if inlines.is_empty() {
+            self.lines.push(String::new());
+        }
+
+        for inline in inlines {
+            match inline {
+                Inline::LineBreak => self.open = false,
+                Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => {
+                    if !self.open {
+                        self.lines.push(String::new());
+                        self.open = true;
+                    }
+                    self.lines.last_mut().unwrap().push_str(s);
+                }
+                _ => {}
+            }
+        }
+        self.open = false;
+    }
+
+    fn finish(self) -> String {
+        // blank lines around the code would be part of the code block
+        let blank = |line: &String| line.trim().is_empty();
+        let start = self.lines.iter().position(|line| !blank(line));
+        let end = self.lines.iter().rposition(|line| !blank(line));
+        match (start, end) {
+            (Some(start), Some(end)) => self.lines[start..=end].join("\n"),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Writes a fenced code block, with a fence longer than any in the code.
+fn fence(lang: &str, code: &str, w: &mut dyn Write) -> io::Result<()> {
+    let mut fence = "```".to_string();
+    while code.contains(&fence) {
+        fence.push('`');
+    }
+    writeln!(w, "{fence}{lang}\n{code}\n{fence}\n")
+}
+
+/// Writes a GFM table, followed by a blank line.
+fn table(table: &Table, w: &mut dyn Write) -> io::Result<()> {
+    for (i, row) in table.rows.iter().enumerate() {
+        write!(w, "|")?;
+        for cell in row {
+            write!(w, " {} |", self::cell(cell))?;
+        }
+        writeln!(w)?;
+
+        if i == 0 {
+            write!(w, "|")?;
+            for align in &table.align {
+                let delim = match align {
+                    Align::Left => "---",
+                    Align::Center => ":-:",
+                    Align::Right => "--:",
+                };
+                write!(w, " {delim} |")?;
+            }
+            writeln!(w)?;
+        }
+    }
+
+    writeln!(w)
+}
+
+/// Renders the text of a table cell, which can't have any breaks.
+fn cell(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Roman(s) => out += &escape(s),
+            Inline::Italic(s) | Inline::Bold(s) => {
+                let delim = if matches!(inline, Inline::Italic(_)) { "*" } else { "**" };
+
+                // emphasis can't start or end with whitespace
+                let core = s.trim();
+                if core.is_empty() {
+                    out += s;
+                } else {
+                    let start = s.len() - s.trim_start().len();
+                    let end = start + core.len();
+                    let core = escape(core);
+                    out += &format!("{}{delim}{core}{delim}{}", &s[..start], &s[end..]);
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Escapes everything Markdown could mistake for syntax, except inline code,
+/// which is written in backticks in every format and kept as a code span.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            let len = match closing(&rest[ticks..], ticks) {
+                Some(end) => 2 * ticks + end,
+                None => {
+                    // backticks that don't start a code span are text
+                    out += &"\\`".repeat(ticks);
+                    rest = &rest[ticks..];
+                    continue;
+                }
+            };
+            out += &rest[..len];
+            rest = &rest[len..];
+            continue;
+        }
+
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '&'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Returns where the run of exactly `n` backticks that closes a code span
+/// starts in `s`, if there is one.
+fn closing(s: &str, n: usize) -> Option<usize> {
+    let mut i = 0;
+    while let Some(start) = s[i..].find('`').map(|start| i + start) {
+        let len = s[start..].len() - s[start..].trim_start_matches('`').len();
+        if len == n {
+            return Some(start);
+        }
+        i = start + len;
+    }
+    None
+}
@@ -0,0 +1,415 @@
+use std::io::{self, Write};
+
+use super::{roman, Inline, Line, Page, Signature, Table};
+
+/// An indented block (`RS`), and the list (`IP`) open in it, if any.
+#[derive(Default)]
+struct Block {
+    /// How far the block is indented by a display (`Bd`), if it isn't just the
+    /// body of the list item it's in, which mdoc already indents.
+    offset: usize,
+    /// Whether the display is open. Displays don't nest well, so it's closed
+    /// around the displays of code and of inner blocks.
+    display: bool,
+    /// The width of the tags of the open list.
+    list: Option<usize>,
+}
+
+impl Block {
+    /// Opens the display of the block for its text, if it needs one.
+    fn open(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        if self.offset > 0 && !self.display {
+            writeln!(w, ".Bd -ragged -offset {}n", self.offset)?;
+            self.display = true;
+        }
+        Ok(())
+    }
+
+    /// Closes the display of the block for another one, unless a list in it
+    /// is still open. Returns the offset the other display starts from.
+    fn suspend(&mut self, w: &mut dyn Write) -> io::Result<usize> {
+        if self.list.is_some() {
+            return Ok(0);
+        }
+        if self.display {
+            writeln!(w, ".Ed")?;
+            self.display = false;
+        }
+        Ok(self.offset)
+    }
+
+    fn close_list(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        if self.list.take().is_some() {
+            writeln!(w, ".El")?;
+        }
+        Ok(())
+    }
+
+    fn close(mut self, w: &mut dyn Write) -> io::Result<()> {
+        self.close_list(w)?;
+        if self.display {
+            writeln!(w, ".Ed")?;
+        }
+        Ok(())
+    }
+}
+
+/// Translates the man(7) macros of a page into mdoc(7). `NAME` and `SEE ALSO`
+/// get their semantic macros (`Nm`/`Nd` and `Xr`), so that mandoc can index
+/// and cross-reference the pages. Indentation becomes displays and lists.
+pub fn write(page: &Page, w: &mut dyn Write) -> io::Result<()> {
+    // mandoc doesn't need it, but man(1) only runs tbl(1) when asked to
+    if page.has_tables() {
+        writeln!(w, r#"'\" t"#)?;
+    }
+
+    let mut section = "";
+    // the outermost block is the section itself
+    let mut blocks = vec![Block::default()];
+    let mut example = false;
+    for line in &page.lines {
+        let block = blocks.last_mut().unwrap();
+        // anything else goes into the display of the block, which may have
+        // been closed around code or an inner block
+        let control = match line {
+            Line::Control { name, .. } => name.as_str(),
+            _ => "",
+        };
+        if !example && !matches!(control, "RS" | "RE" | "EX" | "EE") {
+            block.open(w)?;
+        }
+
+        match line {
+            Line::Control { name, args } => match (name.as_str(), args.as_slice()) {
+                ("TH", [title, sect, ..]) => {
+                    writeln!(w, ".Dd $Mdocdate$")?;
+                    writeln!(w, ".Dt {} {}", arg(title), arg(sect))?;
+                    writeln!(w, ".Os")?;
+                }
+                ("SH" | "SS", [heading]) => {
+                    while let Some(block) = blocks.pop() {
+                        block.close(w)?;
+                    }
+                    blocks.push(Block::default());
+
+                    if name == "SH" {
+                        section = heading;
+                        writeln!(w, ".Sh {}", arg(heading))?;
+                    } else {
+                        writeln!(w, ".Ss {}", arg(heading))?;
+                    }
+                }
+                ("PP", []) => {
+                    block.close_list(w)?;
+                    writeln!(w, ".Pp")?;
+                }
+                // more paragraphs of the same list item
+                ("IP", [tag, ..]) if tag.is_empty() && block.list.is_some() => {
+                    writeln!(w, ".Pp")?;
+                }
+                ("IP", [tag, width @ ..]) => {
+                    if block.list.is_none() {
+                        let width = width.first().and_then(|w| w.parse().ok()).unwrap_or(2);
+                        writeln!(w, ".Bl -tag -width {width}n")?;
+                        block.list = Some(width);
+                    }
+                    writeln!(w, ".It {}", arg(tag))?;
+                }
+                // code can't be indented any further, so it stays as it is
+                ("RS", _) if example => blocks.push(Block::default()),
+                ("RS", args) => {
+                    let indent = args.first().and_then(|n| n.parse().ok());
+                    // inside a list item, indentation is relative to its body
+                    let list = block.list.unwrap_or_default();
+                    let offset = indent.unwrap_or(list).saturating_sub(list);
+                    let outer = block.suspend(w)?;
+                    blocks.push(Block {
+                        offset: outer + offset,
+                        display: false,
+                        list: None,
+                    });
+                }
+                ("RE", []) => {
+                    if blocks.len() > 1 {
+                        blocks.pop().unwrap().close(w)?;
+                    }
+                }
+                ("EX", _) => {
+                    match block.suspend(w)? {
+                        0 => writeln!(w, ".Bd -literal")?,
+                        offset => writeln!(w, ".Bd -literal -offset {offset}n")?,
+                    }
+                    example = true;
+                }
+                ("EE", []) => {
+                    writeln!(w, ".Ed")?;
+                    example = false;
+                }
+                // plain roff requests mean the same thing in both
+                (name, args) => {
+                    write!(w, ".{name}")?;
+                    for a in args {
+                        write!(w, " {}", arg(a))?;
+                    }
+                    writeln!(w)?;
+                }
+            },
+            Line::Text(inlines) => match section {
+                "NAME" => name(inlines, w)?,
+                "SEE ALSO" => see_also(inlines, w)?,
+                _ => text(inlines, w)?,
+            },
+            Line::Table(table) => tbl(table, w)?,
+            Line::Signature(signature) => function(signature, w)?,
+        }
+    }
+
+    while let Some(block) = blocks.pop() {
+        block.close(w)?;
+    }
+
+    Ok(())
+}
+
+/// Splits a `name - description` line into `Nm` and `Nd`.
+fn name(inlines: &[Inline], w: &mut dyn Write) -> io::Result<()> {
+    let line: String = inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => s.as_str(),
+            Inline::LineBreak => " ",
+            Inline::LinkStart(_) | Inline::LinkEnd => "",
+            Inline::ColorStart(_) | Inline::ColorEnd => "",
+            Inline::Control(..) | Inline::Table(_) => unreachable!("split off by Page::text"),
+        })
+        .collect();
+
+    if let Some((name, desc)) = line.split_once(" - ") {
+        writeln!(w, ".Nm {}", arg(name.trim()))?;
+        writeln!(w, ".Nd {}", escape(desc.trim()))
+    } else {
+        writeln!(w, ".Nm {}", arg(line.trim()))
+    }
+}
+
+/// Turns every bold `name(sect)` entry into an `Xr`, keeping the rest as text.
+fn see_also(inlines: &[Inline], w: &mut dyn Write) -> io::Result<()> {
+    let mut inlines = inlines.iter().peekable();
+    while let Some(inline) = inlines.next() {
+        match inline {
+            Inline::Bold(name) => {
+                let sect = match inlines.peek() {
+                    Some(Inline::Roman(s)) => s
+                        .strip_prefix('(')
+                        .and_then(|s| s.split_once(')')),
+                    _ => None,
+                };
+
+                if let Some((sect, rest)) = sect {
+                    inlines.next();
+                    write!(w, ".Xr {} {}", arg(name), arg(sect))?;
+                    for punct in rest.split_whitespace() {
+                        write!(w, " {}", arg(punct))?;
+                    }
+                    writeln!(w)?;
+                } else {
+                    text(std::slice::from_ref(inline), w)?;
+                }
+            }
+            Inline::LineBreak => writeln!(w, ".br")?,
+            // mandoc warns about trailing whitespace
+            Inline::Roman(s) if !s.trim().is_empty() => text(&[roman(s.trim())], w)?,
+            Inline::Roman(_) => {}
+            _ => text(std::slice::from_ref(inline), w)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a text line, turning links into `Lk` (or `Mt`) macros.
+fn text(inlines: &[Inline], w: &mut dyn Write) -> io::Result<()> {
+    let mut at_line_start = true;
+    let mut inlines = inlines.iter();
+    while let Some(inline) = inlines.next() {
+        match inline {
+            Inline::LineBreak => {
+                if !at_line_start {
+                    writeln!(w)?;
+                }
+                writeln!(w, ".br")?;
+                at_line_start = true;
+                continue;
+            }
+            Inline::Roman(s) => {
+                let s = trim_before_link(s, inlines.as_slice());
+                if at_line_start && s.starts_with('.') {
+                    write!(w, r"\&")?;
+                }
+                write!(w, "{}", escape(s))?;
+            }
+            Inline::Italic(s) => {
+                let s = trim_before_link(s, inlines.as_slice());
+                write!(w, r"\fI{}\fR", escape(s))?
+            }
+            Inline::Bold(s) => {
+                let s = trim_before_link(s, inlines.as_slice());
+                write!(w, r"\fB{}\fR", escape(s))?
+            }
+            Inline::LinkStart(url) => {
+                let mut label = String::new();
+                for inline in inlines.by_ref() {
+                    match inline {
+                        Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => label += s,
+                        Inline::LineBreak => label.push(' '),
+                        Inline::LinkStart(_) | Inline::LinkEnd => break,
+                        Inline::ColorStart(_) | Inline::ColorEnd => {}
+                        Inline::Control(..) | Inline::Table(_) => {
+                            unreachable!("split off by Page::text")
+                        }
+                    }
+                }
+
+                if !at_line_start {
+                    writeln!(w)?;
+                }
+                if let Some(address) = url.strip_prefix("mailto:") {
+                    write!(w, ".Mt {}", arg(address))?;
+                } else {
+                    write!(w, ".Lk {}", arg(url))?;
+                    if label.trim() != url {
+                        write!(w, " {}", arg(label.trim()))?;
+                    }
+                }
+
+                // closing punctuation has to be passed to the macro, or it
+                // would be separated by a space
+                let mut rest = "";
+                if let Some(Inline::Roman(s)) = inlines.as_slice().first() {
+                    let end = s.find(|c| !".,:;)]?!".contains(c)).unwrap_or(s.len());
+                    for punct in s[..end].chars() {
+                        write!(w, " {punct}")?;
+                    }
+                    rest = &s[end..];
+                    inlines.next();
+                }
+                writeln!(w)?;
+                at_line_start = true;
+
+                // the text after a macro line mustn't start with whitespace
+                if !rest.trim().is_empty() {
+                    let rest = trim_before_link(rest.trim_start(), inlines.as_slice());
+                    text(&[roman(rest)], w)?;
+                }
+                continue;
+            }
+            Inline::LinkEnd => continue,
+            // mandoc skips these, but groff shows them
+            Inline::ColorStart(color) => write!(w, r"\m[{}]", color.name())?,
+            Inline::ColorEnd => write!(w, r"\m[]")?,
+            Inline::Control(..) | Inline::Table(_) => unreachable!("split off by Page::text"),
+        }
+        at_line_start = false;
+    }
+
+    if !at_line_start {
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a table as tbl(1) input, which mandoc reads in mdoc pages too.
+fn tbl(table: &Table, w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, ".TS")?;
+    for format in table.tbl_format() {
+        writeln!(w, "{format}")?;
+    }
+
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut at_line_start = true;
+        for (j, cell) in row.iter().enumerate() {
+            if j > 0 {
+                write!(w, "\t")?;
+            }
+            if Table::tbl_special(cell) {
+                write!(w, r"\&")?;
+                at_line_start = false;
+            }
+            for inline in cell {
+                match inline {
+                    Inline::Roman(s) => {
+                        if at_line_start && s.starts_with('.') {
+                            write!(w, r"\&")?;
+                        }
+                        write!(w, "{}", escape(s))?
+                    }
+                    Inline::Italic(s) => write!(w, r"\fI{}\fR", escape(s))?,
+                    Inline::Bold(s) => write!(w, r"\fB{}\fR", escape(s))?,
+                    _ => continue,
+                }
+                at_line_start = false;
+            }
+            at_line_start = false;
+        }
+        writeln!(w)?;
+
+        // a rule under the header
+        if i == 0 {
+            writeln!(w, "_")?;
+        }
+    }
+
+    writeln!(w, ".TE")
+}
+
+/// Writes a function signature with `Fo`/`Fa`/`Fc`, so that mandoc can tell
+/// its name and arguments apart.
+fn function(signature: &Signature, w: &mut dyn Write) -> io::Result<()> {
+    if !signature.qualifiers.is_empty() {
+        writeln!(w, "{}", escape(&signature.qualifiers))?;
+    }
+
+    writeln!(w, ".Fo {}", arg(&signature.name))?;
+    for a in &signature.args {
+        writeln!(w, ".Fa {}", arg(a))?;
+    }
+    writeln!(w, ".Fc")?;
+
+    if !signature.output.is_empty() {
+        writeln!(w, "{}", escape(&signature.output))?;
+    }
+    // the where clause goes on a line of its own, as in code
+    if !signature.bounds.is_empty() {
+        writeln!(w, ".br")?;
+        writeln!(w, "{}", escape(&signature.bounds))?;
+    }
+
+    Ok(())
+}
+
+/// Drops trailing whitespace before a link, which goes on its own line.
+fn trim_before_link<'a>(s: &'a str, next: &[Inline]) -> &'a str {
+    match next.first() {
+        Some(Inline::LinkStart(_)) => s.trim_end(),
+        _ => s,
+    }
+}
+
+/// Escapes text so that mandoc prints it verbatim.
+fn escape(s: &str) -> String {
+    s.replace('\\', r"\e")
+        .replace('-', r"\-")
+        .replace('\'', r"\(aq")
+        .replace("\n.", "\n\\&.")
+}
+
+/// Escapes and, if needed, quotes a macro argument.
+fn arg(s: &str) -> String {
+    let s = escape(s).replace('"', r"\(dq");
+    if s.contains(' ') || s.is_empty() {
+        format!("\"{s}\"")
+    } else {
+        s
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{self, Write};
+
+use super::{Align, Inline, Line, Page, Table};
+
+/// How far section bodies are indented, like man(1) does.
+const INDENT: &str = "    ";
+
+/// Writes a page as plain text, filling text lines and indenting them the same
+/// way roff would, and dropping all font changes.
+pub fn write(page: &Page, w: &mut dyn Write) -> io::Result<()> {
+    let mut at_line_start = true;
+    let mut pending_space = false;
+    // a new paragraph owes a blank line, if there's text in the section to
+    // separate it from
+    let mut pending_blank = false;
+    let mut written = false;
+    // the indentation past `INDENT`: of `RS` (the outer ones saved in
+    // `margins`), and of the paragraph started by `IP`
+    let mut margins = Vec::new();
+    let mut margin = 0;
+    let mut hang = 0;
+    // the URL and text of the link being written
+    let mut link: Option<(&str, String)> = None;
+    for line in &page.lines {
+        match line {
+            Line::Control { name, args } => {
+                if !at_line_start {
+                    writeln!(w)?;
+                    at_line_start = true;
+                }
+                pending_space = false;
+
+                match (name.as_str(), args.as_slice()) {
+                    ("TH", [title, sect, ..]) => writeln!(w, "{title}({sect})")?,
+                    ("SH", [name]) => writeln!(w, "\n{name}")?,
+                    ("SS", [name]) => writeln!(w, "\n  {name}")?,
+                    ("PP", []) => {
+                        pending_blank = written;
+                        hang = 0;
+                    }
+                    ("IP", [tag, width @ ..]) => {
+                        hang = width.first().and_then(|w| w.parse().ok()).unwrap_or(hang);
+                        pending_blank = written;
+                        if !tag.is_empty() {
+                            if pending_blank {
+                                writeln!(w)?;
+                            }
+                            pending_blank = false;
+                            written = true;
+                            let tag = format!("{tag:<hang$}");
+                            write!(w, "{INDENT}{}{tag}", " ".repeat(margin))?;
+                            // like roff, a tag too wide for its indentation
+                            // gets a line of its own
+                            at_line_start = tag.len() > hang;
+                            if at_line_start {
+                                writeln!(w)?;
+                            }
+                        }
+                    }
+                    ("RS", args) => {
+                        margins.push(margin);
+                        margin += args.first().and_then(|n| n.parse().ok()).unwrap_or(hang);
+                        hang = 0;
+                    }
+                    ("RE", []) => {
+                        margin = margins.pop().unwrap_or_default();
+                        hang = 0;
+                    }
+                    // `EX` and `EE` only break the line
+                    _ => {}
+                }
+
+                // sections start over at the left margin
+                if matches!(name.as_str(), "SH" | "SS") {
+                    (margins, margin, hang) = (Vec::new(), 0, 0);
+                    pending_blank = false;
+                    written = false;
+                }
+            }
+            Line::Text(inlines) => {
+                for inline in inlines {
+                    let s = match inline {
+                        Inline::LineBreak => {
+                            if !at_line_start {
+                                writeln!(w)?;
+                            }
+                            at_line_start = true;
+                            pending_space = false;
+                            continue;
+                        }
+                        Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => {
+                            if let Some((_, text)) = &mut link {
+                                text.push_str(s);
+                            }
+                            s.clone()
+                        }
+                        Inline::LinkStart(url) => {
+                            link = Some((url, String::new()));
+                            continue;
+                        }
+                        Inline::ColorStart(_) | Inline::ColorEnd => continue,
+                        // print the URL after the text, unless that's the URL
+                        Inline::LinkEnd => match link.take() {
+                            Some((url, text))
+                                if text.trim() != url.trim_start_matches("mailto:") =>
+                            {
+                                pending_space = true;
+                                format!("<{url}>")
+                            }
+                            _ => continue,
+                        },
+                        Inline::Control(..) | Inline::Table(_) => {
+                            unreachable!("split off by Page::text")
+                        }
+                    };
+
+                    if s.is_empty() {
+                        continue;
+                    }
+
+                    if at_line_start {
+                        if pending_blank {
+                            writeln!(w)?;
+                            pending_blank = false;
+                        }
+                        write!(w, "{INDENT}{}", " ".repeat(margin + hang))?;
+                    } else if pending_space {
+                        write!(w, " ")?;
+                    }
+                    pending_space = false;
+                    write!(w, "{}", s.replace('\n', " "))?;
+                    at_line_start = false;
+                    written = true;
+                }
+
+                // consecutive text lines get filled into one
+                pending_space = !at_line_start;
+            }
+            Line::Signature(_) => unreachable!("written as code by Page::write"),
+            Line::Table(t) => {
+                if !at_line_start {
+                    writeln!(w)?;
+                    at_line_start = true;
+                }
+                if pending_blank {
+                    writeln!(w)?;
+                    pending_blank = false;
+                }
+                pending_space = false;
+
+                let indent = INDENT.to_string() + &" ".repeat(margin + hang);
+                table(t, &indent, w)?;
+                written = true;
+            }
+        }
+    }
+
+    if !at_line_start {
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a table with its columns padded to line up, and a rule under the
+/// header.
+fn table(table: &Table, indent: &str, w: &mut dyn Write) -> io::Result<()> {
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    cell.iter()
+                        .map(|inline| match inline {
+                            Inline::Roman(s) | Inline::Italic(s) | Inline::Bold(s) => s.as_str(),
+                            _ => "",
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut widths = vec![0; table.align.len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = cell.chars().count().max(*width);
+        }
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut line = Vec::new();
+        for (j, (&width, align)) in widths.iter().zip(&table.align).enumerate() {
+            let cell = row.get(j).map_or("", String::as_str);
+            line.push(match align {
+                Align::Left => format!("{cell:<width$}"),
+                Align::Center => format!("{cell:^width$}"),
+                Align::Right => format!("{cell:>width$}"),
+            });
+        }
+        writeln!(w, "{indent}{}", line.join("  ").trim_end())?;
+
+        if i == 0 {
+            let rule: Vec<_> = widths.iter().map(|&width| "-".repeat(width)).collect();
+            writeln!(w, "{indent}{}", rule.join("  "))?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,21 +1,21 @@
-use roff::{bold, italic, line_break, roman, Inline};
 use rustdoc_types::{
-    Crate, GenericArg, GenericArgs, GenericBound, GenericParamDef, GenericParamDefKind as GPDK,
-    Term, TraitBoundModifier, WherePredicate,
+    GenericArg, GenericArgs, GenericBound, GenericParamDef, GenericParamDefKind as GPDK,
+    Term, TraitBoundModifier, TypeBindingKind, WherePredicate,
 };
 
-use super::render_type;
+use super::format::{bold, italic, line_break, roman, Inline};
+use super::{render_type, Context, Result};
 
 pub fn render_generics(
-    cr: &Crate,
+    cx: &Context,
     name: &str,
     generics: &[GenericParamDef],
     mut depth: usize,
     page: &mut Vec<Inline>,
-) {
+) -> Result {
     if generics.is_empty() {
         page.push(bold(name));
-        return;
+        return Ok(());
     }
 
     page.append(&mut if generics.len() < 3 {
@@ -68,44 +68,18 @@ pub fn render_generics(
             } => {
                 page.push(italic(&param.name));
 
-                let mut first = true;
-                for bound in bounds {
-                    if first {
-                        page.push(roman(": "));
-                    } else {
-                        page.push(roman(" + "));
-                    }
-                    first = false;
-
-                    match bound {
-                        GenericBound::TraitBound {
-                            trait_,
-                            modifier,
-                            .. // TODO: when is this populated?
-                        } => {
-                            if *modifier == TraitBoundModifier::Maybe {
-                                page.push(roman("?"));
-                            }
-
-                            if let Some(args) = &trait_.args {
-                                render_generics_args(cr, &trait_.name, args, depth + 1, page);
-                            } else {
-                                page.push(bold(&trait_.name));
-                            }
-                        }
-                        GenericBound::Outlives(lt) => {
-                            page.push(roman(lt));
-                        }
-                    }
+                if !bounds.is_empty() {
+                    page.push(roman(": "));
+                    render_generics_bounds(cx, bounds, depth, page)?;
+                }
 
-                    if let Some(default) = default {
-                        page.push(roman(" = "));
-                        render_type(cr, default, depth + 1, page);
-                    }
+                if let Some(default) = default {
+                    page.push(roman(" = "));
+                    render_type(cx, default, depth + 1, page)?;
                 }
             }
             GPDK::Const { type_, default } => {
-                render_type(cr, type_, depth + 1, page);
+                render_type(cx, type_, depth + 1, page)?;
                 if let Some(default) = default {
                     page.push(roman(" = "));
                     page.push(roman(default));
@@ -120,36 +94,43 @@ pub fn render_generics(
         page.push(line_break());
         page.push(roman(">"));
     }
+
+    Ok(())
 }
 
 pub fn render_generics_args(
-    cr: &Crate,
+    cx: &Context,
     name: &str,
     generics: &GenericArgs,
+    depth: usize,
+    page: &mut Vec<Inline>,
+) -> Result {
+    page.push(bold(name));
+    render_args(cx, generics, depth, page)
+}
+
+/// Renders the arguments of a path segment, without its name.
+pub fn render_args(
+    cx: &Context,
+    generics: &GenericArgs,
     mut depth: usize,
     page: &mut Vec<Inline>,
-) {
+) -> Result {
     match generics {
-        // TODO: handle bindings
-        GenericArgs::AngleBracketed { args, .. } => {
-            if args.is_empty() {
-                page.push(bold(name));
-                return;
+        GenericArgs::AngleBracketed { args, bindings } => {
+            let len = args.len() + bindings.len();
+            if len == 0 {
+                return Ok(());
             }
 
-            page.append(&mut if args.len() < 3 {
-                vec![bold(name), roman("<")]
+            page.append(&mut if len < 3 {
+                vec![roman("<")]
             } else {
                 depth += 1;
-                vec![
-                    bold(name),
-                    roman("<"),
-                    line_break(),
-                    roman("  ".repeat(depth)),
-                ]
+                vec![roman("<"), line_break(), roman("  ".repeat(depth))]
             });
 
-            let sep = if args.len() < 3 {
+            let sep = if len < 3 {
                 |page: &mut Vec<Inline>, _| page.push(roman(", "))
             } else {
                 |page: &mut Vec<Inline>, depth| {
@@ -168,7 +149,7 @@ pub fn render_generics_args(
 
                 match param {
                     GenericArg::Lifetime(lt) => page.push(roman(lt)),
-                    GenericArg::Type(typ) => render_type(cr, typ, depth + 1, page),
+                    GenericArg::Type(typ) => render_type(cx, typ, depth + 1, page)?,
                     GenericArg::Const(co) => {
                         page.push(roman(format!("const {}{:?}", co.expr, co.value)));
                     }
@@ -176,7 +157,30 @@ pub fn render_generics_args(
                 }
             }
 
-            if args.len() < 3 {
+            // `Item = T` and `Item: Display`
+            for binding in bindings {
+                if !first {
+                    sep(page, depth);
+                }
+                first = false;
+
+                // associated types are italic, like in qualified paths
+                page.push(italic(&binding.name));
+                render_args(cx, &binding.args, depth, page)?;
+
+                match &binding.binding {
+                    TypeBindingKind::Equality(term) => {
+                        page.push(roman(" = "));
+                        render_term(cx, term, depth + 1, page)?;
+                    }
+                    TypeBindingKind::Constraint(bounds) => {
+                        page.push(roman(": "));
+                        render_generics_bounds(cx, bounds, depth, page)?;
+                    }
+                }
+            }
+
+            if len < 3 {
                 page.push(roman(">"));
             } else {
                 page.push(line_break());
@@ -185,15 +189,10 @@ pub fn render_generics_args(
         }
         GenericArgs::Parenthesized { inputs, output } => {
             page.append(&mut if inputs.len() < 3 {
-                vec![bold(name), roman("(")]
+                vec![roman("(")]
             } else {
                 depth += 1;
-                vec![
-                    bold(name),
-                    roman("("),
-                    line_break(),
-                    roman("  ".repeat(depth)),
-                ]
+                vec![roman("("), line_break(), roman("  ".repeat(depth))]
             });
 
             let sep = if inputs.len() < 3 {
@@ -212,7 +211,7 @@ pub fn render_generics_args(
                     sep(page, depth);
                 }
                 first = false;
-                render_type(cr, arg, depth + 1, page);
+                render_type(cx, arg, depth + 1, page)?;
             }
 
             if inputs.len() < 3 {
@@ -225,18 +224,20 @@ pub fn render_generics_args(
 
             if let Some(output) = output {
                 page.push(roman(" -> "));
-                render_type(cr, output, depth + 1, page);
+                render_type(cx, output, depth + 1, page)?;
             }
         }
     }
+
+    Ok(())
 }
 
 pub fn render_generics_bounds(
-    cr: &Crate,
+    cx: &Context,
     bounds: &[GenericBound],
     depth: usize,
     page: &mut Vec<Inline>,
-) {
+) -> Result {
     let mut first = true;
     for bound in bounds {
         if !first {
@@ -247,17 +248,26 @@ pub fn render_generics_bounds(
         match bound {
             GenericBound::TraitBound {
                 trait_,
+                generic_params,
                 modifier,
-                .. // TODO: when is this populated?
             } => {
                 if *modifier == TraitBoundModifier::Maybe {
                     page.push(roman("?"));
                 }
 
+                // `for<'a> Fn(&'a T)`
+                if !generic_params.is_empty() {
+                    render_generics(cx, "for", generic_params, depth + 1, page)?;
+                    page.push(roman(" "));
+                }
+
+                cx.reference(&trait_.id);
+
+                let name = cx.path_name(&trait_.id, &trait_.name);
                 if let Some(args) = &trait_.args {
-                    render_generics_args(cr, &trait_.name, args, depth + 1, page);
+                    render_generics_args(cx, &name, args, depth + 1, page)?;
                 } else {
-                    page.push(bold(&trait_.name));
+                    page.push(bold(name));
                 }
             }
             GenericBound::Outlives(lt) => {
@@ -265,11 +275,18 @@ pub fn render_generics_bounds(
             }
         }
     }
+
+    Ok(())
 }
 
-pub fn render_where(cr: &Crate, bounds: &[WherePredicate], depth: usize, page: &mut Vec<Inline>) {
+pub fn render_where(
+    cx: &Context,
+    bounds: &[WherePredicate],
+    depth: usize,
+    page: &mut Vec<Inline>,
+) -> Result {
     if bounds.is_empty() {
-        return;
+        return Ok(());
     }
     page.push(roman("where"));
 
@@ -288,29 +305,38 @@ pub fn render_where(cr: &Crate, bounds: &[WherePredicate], depth: usize, page: &
                 bounds,
                 generic_params,
             } => {
+                // `for<'a> F: Fn(&'a T)`
                 if !generic_params.is_empty() {
-                    // I've yet to discover what these are for
-                    panic!("generics params: {generic_params:#?}");
+                    render_generics(cx, "for", generic_params, depth + 2, page)?;
+                    page.push(roman(" "));
                 }
-                render_type(cr, type_, depth + 2, page);
+                render_type(cx, type_, depth + 2, page)?;
                 page.push(roman(": "));
-                render_generics_bounds(cr, bounds, depth + 2, page);
+                render_generics_bounds(cx, bounds, depth + 2, page)?;
             }
             WherePredicate::RegionPredicate { lifetime, bounds } => {
                 page.push(roman(lifetime));
-                render_generics_bounds(cr, bounds, depth + 2, page);
+                render_generics_bounds(cx, bounds, depth + 2, page)?;
             }
             WherePredicate::EqPredicate { lhs, rhs } => {
-                render_type(cr, lhs, depth + 2, page);
+                render_type(cx, lhs, depth + 2, page)?;
                 page.push(roman(" = "));
 
-                match rhs {
-                    Term::Type(t) => render_type(cr, t, depth + 2, page),
-                    Term::Constant(c) => page.push(roman(c.value.as_ref().unwrap())),
-                }
+                render_term(cx, rhs, depth + 2, page)?;
             }
         }
     }
 
     page.push(line_break());
+
+    Ok(())
+}
+
+fn render_term(cx: &Context, term: &Term, depth: usize, page: &mut Vec<Inline>) -> Result {
+    match term {
+        Term::Type(t) => render_type(cx, t, depth, page)?,
+        Term::Constant(c) => page.push(roman(c.value.as_ref().unwrap_or(&c.expr))),
+    }
+
+    Ok(())
 }
@@ -0,0 +1,125 @@
+use errata::{error, FallibleExt};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The user's own man hierarchy, which `man` searches by default. This is the
+/// XDG data directory on every platform, as `man` doesn't know about others
+/// (like `~/Library/Application Support` on macOS).
+pub fn default_prefix() -> PathBuf {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")))
+        .unwrap_or_else(|| "./".into());
+    data.join("man")
+}
+
+/// The directory pages get installed into. `man` looks for section `3r` in
+/// `man3`, along with the rest of section 3.
+pub fn pages_dir(prefix: &Path) -> PathBuf {
+    prefix.join("man3")
+}
+
+/// Where the manifests of installed crates are kept. mandb only looks at the
+/// `man*` directories, so this doesn't get in its way.
+fn manifest_dir(prefix: &Path) -> PathBuf {
+    prefix.join(".manners")
+}
+
+/// Reads the files an install of `krate` recorded, relative to the prefix.
+fn read_manifest(prefix: &Path, krate: &str) -> BTreeSet<PathBuf> {
+    fs::read_to_string(manifest_dir(prefix).join(krate))
+        .map(|manifest| manifest.lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+/// Collects the files recorded by every manifest except that of `krate`.
+/// Alias pages can be shared between crates, and have to stay while any of
+/// them is installed.
+fn recorded_by_others(prefix: &Path, krate: &str) -> BTreeSet<PathBuf> {
+    let Ok(entries) = fs::read_dir(manifest_dir(prefix)) else {
+        return BTreeSet::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != krate)
+        .flat_map(|name| read_manifest(prefix, &name))
+        .collect()
+}
+
+/// Records the files just installed for `krate`, removing any that its
+/// previous install had but this one didn't.
+pub fn record(prefix: &Path, krate: &str, files: &BTreeSet<PathBuf>) {
+    let files: BTreeSet<_> = files
+        .iter()
+        .filter_map(|file| file.strip_prefix(prefix).ok())
+        .map(Path::to_path_buf)
+        .collect();
+
+    let others = recorded_by_others(prefix, krate);
+    for stale in read_manifest(prefix, krate).difference(&files) {
+        if !others.contains(stale) {
+            remove(prefix, stale);
+        }
+    }
+
+    let dir = manifest_dir(prefix);
+    fs::create_dir_all(&dir).fail("failed to create manifest directory");
+    let manifest: String = files
+        .iter()
+        .map(|file| file.display().to_string() + "\n")
+        .collect();
+    fs::write(dir.join(krate), manifest).fail("failed to write manifest");
+}
+
+/// Removes exactly the files the install of `krate` recorded, except those
+/// another installed crate still needs.
+pub fn uninstall(prefix: &Path, krate: &str) {
+    // cargo allows dashes in package names, but rustdoc names crates with
+    // underscores
+    let krate = krate.replace('-', "_");
+    let manifest = manifest_dir(prefix).join(&krate);
+    if !manifest.exists() {
+        error!("{krate} isn't installed in {}", prefix.display());
+    }
+
+    let others = recorded_by_others(prefix, &krate);
+    let files = read_manifest(prefix, &krate);
+    for file in files.difference(&others) {
+        remove(prefix, file);
+    }
+
+    fs::remove_file(manifest).fail("failed to remove manifest");
+    eprintln!("- uninstalled {krate} ({} files)", files.len());
+}
+
+fn remove(prefix: &Path, file: &Path) {
+    if let Err(e) = fs::remove_file(prefix.join(file)) {
+        if e.kind() != ErrorKind::NotFound {
+            eprintln!("failed to remove {}: {e}", file.display());
+        }
+    }
+}
+
+/// Refreshes the whatis database of the hierarchy, so that `whatis` and
+/// `apropos` know about the pages. Uses mandb(8), or mandoc's makewhatis(8) on
+/// systems without man-db.
+pub fn update_database(prefix: &Path) {
+    for (command, args) in [("mandb", &["-q"][..]), ("makewhatis", &[][..])] {
+        match Command::new(command).args(args).arg(prefix).status() {
+            Ok(status) if !status.success() => eprintln!("{command} failed: {status}"),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => eprintln!("failed to run {command}: {e}"),
+        }
+        return;
+    }
+
+    eprintln!("found neither mandb nor makewhatis; whatis and apropos won't see the new pages");
+}
@@ -1,18 +1,28 @@
 #![allow(clippy::ptr_arg)] // FIXME: why is clippy doing this
 
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package};
 use errata::{FallibleExt, error};
 use flate2::{Compression, GzBuilder};
-use roff::Roff;
 use rustdoc_types::{Crate, Id, Item, ItemEnum, Module};
 use sarge::prelude::*;
 use serde_json::from_str;
 
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
+mod cache;
 mod gen;
+mod install;
 mod markdown;
 
+use cache::Cache;
+use gen::format::{Format, Page};
+
 sarge! {
     Args,
 
@@ -29,6 +39,36 @@ sarge! {
     > "Defaults to all."
     #ok features: Vec<String>,
 
+    > "Workspace members to document. Defaults to all."
+    #ok 'p' package: Vec<String>,
+
+    > "Workspace members to skip."
+    #ok exclude: Vec<String>,
+
+    > "Also document all (non-dev) dependencies."
+    deps: bool,
+
+    > "The output format: man, mdoc, text or markdown."
+    format: String = "man".to_string(),
+
+    > "How types in signatures are written: short, full or crate-relative."
+    paths: String = "short".to_string(),
+
+    > "How Rust code in docs is highlighted: none, fonts or colors."
+    highlight: String = "none".to_string(),
+
+    > "Make short-name aliases symlinks instead of `.so` pages."
+    symlink: bool,
+
+    > "Where `install` puts pages. Defaults to ~/.local/share/man."
+    #ok prefix: String,
+
+    > "How many pages to generate at once. Defaults to the number of CPUs."
+    #ok 'J' jobs: usize,
+
+    > "Stop at the first page that fails to generate, instead of skipping it."
+    strict: bool,
+
     > "The output directory."
     'o' output: String = "output".to_string(),
 
@@ -45,60 +85,232 @@ fn main() {
         return;
     }
 
+    let prefix = args
+        .prefix
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(install::default_prefix);
+
+    // the first "file" is the program itself
+    let (installing, files) = match files.get(1).map(String::as_str) {
+        Some("install") => (true, &files[2..]),
+        Some("uninstall") => {
+            if files.len() < 3 {
+                error!("expected at least 1 crate to uninstall");
+            }
+            for krate in &files[2..] {
+                install::uninstall(&prefix, krate);
+            }
+            install::update_database(&prefix);
+            return;
+        }
+        _ => (false, &files[1..]),
+    };
+
     if files.is_empty() {
         error!("expected at least 1 target file");
     }
 
-    let output = Path::new(&args.output);
-    if args.clean && output.exists() {
+    let format: Format = args.format.parse().fail("invalid format");
+    let paths: gen::PathStyle = args.paths.parse().fail("invalid path style");
+    let highlight: gen::Highlight = args.highlight.parse().fail("invalid highlighting");
+    let jobs = args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .max(1);
+
+    let output = if installing {
+        if !matches!(format, Format::Man | Format::Mdoc) {
+            error!("only man and mdoc pages can be installed");
+        }
+        install::pages_dir(&prefix)
+    } else {
+        PathBuf::from(&args.output)
+    };
+    let output = output.as_path();
+
+    // the pages of other crates live in the MANPATH too
+    if args.clean && !installing && output.exists() {
         fs::remove_dir_all(output).fail("failed to clean output directory");
     }
 
-    for file in &files[1..] {
-        let docs_path = if !args.json {
-            let mut data_dir = dirs::data_dir().unwrap_or_else(|| "./".into());
-            data_dir.push("manners");
-            fs::create_dir_all(&data_dir).fail("failed to create data directory");
-
-            rustdoc_json::Builder::default()
-                .toolchain("nightly")
-                .target_dir(&data_dir)
-                .all_features(!args.features.as_ref().is_some_and(|v| v.is_empty()))
-                .features(args.features.as_deref().unwrap_or(&[]))
-                .manifest_path(file)
-                .build()
-                // TODO: rustdoc-json has terrible error practices
-                .fail("rustdoc-json failed")
+    let mut docs = Vec::new();
+    for file in files {
+        if args.json {
+            docs.push((PathBuf::from(file), None));
         } else {
-            file.into()
-        };
+            docs.extend(build(&args, file));
+        }
+    }
+
+    // rustdoc names each file after its crate
+    let opts = gen::Options {
+        max_width: args.max_width,
+        documented: docs
+            .iter()
+            .filter_map(|(path, _)| path.file_stem())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect(),
+        features: None,
+        target: (!args.json).then(host).flatten(),
+        paths,
+        highlight,
+        public_paths: HashMap::new(),
+    };
+
+    // anything that changes every page has to invalidate them
+    let salt = format!(
+        "{} {format:?} {}",
+        env!("CARGO_PKG_VERSION"),
+        args.max_width
+    );
+    let cache = Cache::load(output, salt);
 
+    let mut written = Written::new(output, format, cache);
+    for (docs_path, features) in docs {
         let data = fs::read_to_string(docs_path).fail("failed to read JSON documentation");
+        let cr: Crate = from_str(&data).fail("failed to parse JSON documentation");
+        let opts = gen::Options {
+            features,
+            public_paths: gen::public_paths(&cr),
+            ..opts.clone()
+        };
 
-        let cr = from_str(&data).fail("failed to parse JSON documentation");
         fs::create_dir_all(output).fail("failed to create output directory");
 
-        {
-            let (path, root) =
-                gen::gen(&cr, &cr.root, args.max_width).fail("failed to generate manpage");
-            save(root, output.join(path));
-        }
-
         let Some(Item {
             inner: ItemEnum::Module(Module { items, .. }),
+            name: Some(krate),
             ..
         }) = cr.index.get(&cr.root)
         else {
             unreachable!()
         };
 
-        recurse(&cr, items, output, args.max_width);
+        let mut ids = Vec::new();
+        collect(&cr, items, &mut ids);
+        let ids = unique(&cr, &ids, &opts);
+
+        // the same docs and options make the same pages as last time
+        let input = written.cache.hash(&(&data, &opts));
+        written.cache.record_input(krate, input);
+        if written.cache.is_unchanged(krate, input) && reuse(&cr, &ids, &opts, &mut written) {
+            eprintln!("- {krate} is up to date");
+            continue;
+        }
+
+        {
+            let root = gen::gen(&cr, &cr.root, None, &opts).map(|root| {
+                root.map(|root| render(root, &written.cache, &written.dir, format))
+            });
+            log(&cr, &cr.root, &root, args.strict);
+            written.add(&cr, &cr.root, None, root);
+
+            let index = gen::index(&cr, &opts).map(|index| {
+                let index = render(index, &written.cache, &written.dir, format);
+                Some(Rendered { aliases: false, ..index })
+            });
+            log(&cr, &cr.root, &index, args.strict);
+            written.add(&cr, &cr.root, None, index);
+        }
+
+        generate(&cr, &ids, &opts, &mut written, jobs, args.strict);
+    }
+
+    if written.failed > 0 {
+        eprintln!(
+            "failed to generate {} of {} pages",
+            written.failed,
+            written.failed + written.pages.len()
+        );
+    }
+
+    save_aliases(&mut written, args.symlink);
+    written.cache.finish(&written.files);
+
+    if installing {
+        for (krate, files) in &written.files {
+            install::record(&prefix, krate, files);
+        }
+        install::update_database(&prefix);
+    }
+}
+
+/// The pages written so far, and the short names that lead to them.
+struct Written {
+    dir: PathBuf,
+    format: Format,
+    cache: Cache,
+    /// The crate each page belongs to.
+    pages: HashMap<String, String>,
+    aliases: BTreeMap<String, BTreeSet<String>>,
+    /// The files written for each crate.
+    files: BTreeMap<String, BTreeSet<PathBuf>>,
+    /// How many pages failed to generate.
+    failed: usize,
+}
+
+impl Written {
+    fn new(dir: &Path, format: Format, cache: Cache) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            format,
+            cache,
+            pages: HashMap::new(),
+            aliases: BTreeMap::new(),
+            files: BTreeMap::new(),
+            failed: 0,
+        }
+    }
+
+    /// Records a page of the crate `cr`, and the item `id` it documents.
+    fn add(&mut self, cr: &Crate, id: &Id, owner: Option<&Id>, outcome: Outcome) {
+        let rendered = match outcome {
+            Ok(Some(rendered)) => rendered,
+            Ok(None) => return,
+            Err(_) => {
+                self.failed += 1;
+                return;
+            }
+        };
+
+        self.cache.record(&rendered.file, rendered.hash);
+
+        let krate = cr.index[&cr.root].name.clone().unwrap();
+        self.files.entry(krate.clone()).or_default().insert(rendered.file);
+        if rendered.aliases {
+            for alias in gen::aliases(cr, id, owner) {
+                self.aliases
+                    .entry(alias)
+                    .or_default()
+                    .insert(rendered.name.clone());
+            }
+        }
+        self.pages.insert(rendered.name, krate);
     }
 }
 
-fn recurse(cr: &Crate, items: &[Id], output: &Path, max_width: usize) {
-    for id in items {
-        let Some((path, page)) = gen::gen(cr, id, max_width) else {
+/// A page that has been generated, and written if it changed.
+struct Rendered {
+    name: String,
+    file: PathBuf,
+    hash: u64,
+    changed: bool,
+    warnings: Vec<String>,
+    /// Whether the page should get short-name aliases.
+    aliases: bool,
+}
+
+/// What became of the page of an item: written, not needed, or failed.
+type Outcome = Result<Option<Rendered>, gen::PageError>;
+
+/// Prints what happened to the page of `id`. With `strict`, a page that failed
+/// ends the run.
+fn log(cr: &Crate, id: &Id, outcome: &Outcome, strict: bool) {
+    let rendered = match outcome {
+        Ok(Some(rendered)) => rendered,
+        Ok(None) => {
             // if it has no name, it's not important (an import or whatnot)
             if let Some(name) = cr
                 .paths
@@ -108,31 +320,442 @@ fn recurse(cr: &Crate, items: &[Id], output: &Path, max_width: usize) {
             {
                 eprintln!("unsupported item: {name}");
             }
-            continue;
-        };
-        eprintln!("- writing {path}");
-        save(page, output.join(path));
+            return;
+        }
+        Err(e) if strict => error!("{e}"),
+        Err(e) => {
+            eprintln!("{e}");
+            return;
+        }
+    };
+
+    for warning in &rendered.warnings {
+        eprintln!("{warning}");
+    }
+    if rendered.changed {
+        eprintln!("- writing {}", rendered.name);
+    }
+}
+
+/// Writes a generated page into `dir`, unless the cache has it already.
+fn render(generated: gen::Generated, cache: &Cache, dir: &Path, format: Format) -> Rendered {
+    let path = dir.join(&generated.name);
+    let file = file_path(format, path.clone());
+    let hash = cache.hash(&generated.page);
+    let changed = !cache.is_fresh(&file, hash);
+    if changed {
+        save(generated.page, format, path);
+    }
+
+    Rendered {
+        name: generated.name,
+        file,
+        hash,
+        changed,
+        warnings: generated.warnings,
+        aliases: true,
+    }
+}
+
+/// Collects every item of a module tree, in the order its pages get written.
+/// Associated items come right after the type or trait they belong to.
+fn collect(cr: &Crate, items: &[Id], ids: &mut Vec<(Id, Option<Id>)>) {
+    for id in items {
+        ids.push((id.clone(), None));
+        for assoc in gen::assoc_items(cr, id) {
+            ids.push((assoc, Some(id.clone())));
+        }
 
         if let Some(Item {
             inner: ItemEnum::Module(module),
             ..
         }) = cr.index.get(id)
         {
-            recurse(cr, &module.items, output, max_width);
+            collect(cr, &module.items, ids);
+        }
+    }
+}
+
+/// Leaves out the items whose page has the same name as that of an earlier
+/// one, which it would overwrite.
+fn unique(cr: &Crate, ids: &[(Id, Option<Id>)], opts: &gen::Options) -> Vec<(Id, Option<Id>)> {
+    let mut names = HashSet::new();
+    ids.iter()
+        .filter(|(id, owner)| match gen::page_name(cr, opts, id, owner.as_ref()) {
+            Some(name) if names.contains(&name) => {
+                eprintln!("skipping duplicate page: {name}");
+                false
+            }
+            Some(name) => names.insert(name),
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Records the pages the last run wrote for the items of a crate, instead of
+/// generating them again. If any of them is missing, nothing is recorded, and
+/// `false` is returned.
+fn reuse(cr: &Crate, ids: &[(Id, Option<Id>)], opts: &gen::Options, written: &mut Written) -> bool {
+    let krate = cr.index[&cr.root].name.clone().unwrap();
+
+    // the root module and the index have pages of their own
+    let mut pages = vec![
+        (&cr.root, None, gen::page_name(cr, opts, &cr.root, None), true),
+        (&cr.root, None, Some(krate.clone()), false),
+    ];
+    for (id, owner) in ids {
+        pages.push((id, owner.as_ref(), gen::page_name(cr, opts, id, owner.as_ref()), true));
+    }
+
+    let mut reused = Vec::new();
+    for (id, owner, name, aliases) in pages {
+        // items without a page of their own
+        let Some(name) = name else { continue };
+
+        let file = file_path(written.format, written.dir.join(&name));
+        let Some(hash) = written.cache.cached(&krate, &file) else {
+            return false;
+        };
+        let rendered = Rendered {
+            name,
+            file,
+            hash,
+            changed: false,
+            warnings: Vec::new(),
+            aliases,
+        };
+        reused.push((id, owner, rendered));
+    }
+
+    for (id, owner, rendered) in reused {
+        written.add(cr, id, owner, Ok(Some(rendered)));
+    }
+    true
+}
+
+/// Generates and writes the pages of `ids` on `jobs` threads. Their results
+/// are handled in the order of `ids`, however the threads finish, so that the
+/// output and the logs are the same on every run.
+fn generate(
+    cr: &Crate,
+    ids: &[(Id, Option<Id>)],
+    opts: &gen::Options,
+    written: &mut Written,
+    jobs: usize,
+    strict: bool,
+) {
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let (cache, dir, format) = (&written.cache, &written.dir, written.format);
+
+    let results = thread::scope(|s| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                let tx = tx.clone();
+                let next = &next;
+                s.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((id, owner)) = ids.get(i) else { break };
+
+                    let rendered = gen::gen(cr, id, owner.as_ref(), opts).map(|generated| {
+                        generated.map(|generated| render(generated, cache, dir, format))
+                    });
+                    if tx.send((i, rendered)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        // log the results in order as soon as they're ready
+        let mut pending = BTreeMap::new();
+        let mut results = Vec::with_capacity(ids.len());
+        for (i, rendered) in rx {
+            pending.insert(i, rendered);
+            while let Some(rendered) = pending.remove(&results.len()) {
+                // failing here drops `rx`, which stops the workers
+                log(cr, &ids[results.len()].0, &rendered, strict);
+                results.push(rendered);
+            }
+        }
+
+        // keep the message of the first failure
+        for worker in workers {
+            if let Err(panic) = worker.join() {
+                std::panic::resume_unwind(panic);
+            }
+        }
+
+        results
+    });
+
+    for ((id, owner), rendered) in ids.iter().zip(results) {
+        written.add(cr, id, owner.as_ref(), rendered);
+    }
+}
+
+/// Writes a page for every alias that isn't already a page itself. Aliases for
+/// a single page redirect to it, others get a page listing their candidates.
+/// Either way, they belong to the crates of every page they lead to.
+fn save_aliases(written: &mut Written, symlink: bool) {
+    let output = written.dir.clone();
+    let format = written.format;
+    for (alias, targets) in std::mem::take(&mut written.aliases) {
+        if written.pages.contains_key(&alias) {
+            continue;
+        }
+
+        // writing through a symlink from an earlier run would clobber its target
+        let ext = format.extension();
+        let remove_old = || {
+            let _ = fs::remove_file(output.join(format!("{alias}.{ext}")));
+            let _ = fs::remove_file(output.join(format!("{alias}.{ext}.gz")));
+        };
+
+        let file = if targets.len() > 1 {
+            let generated = gen::Generated {
+                name: alias.clone(),
+                page: gen::disambiguation(&alias, &targets),
+                warnings: Vec::new(),
+            };
+
+            // an unchanged page from an earlier run can stay
+            let file = file_path(format, output.join(&alias));
+            let link = fs::symlink_metadata(&file).is_ok_and(|m| m.file_type().is_symlink());
+            if link || !written.cache.is_fresh(&file, written.cache.hash(&generated.page)) {
+                remove_old();
+            }
+
+            let rendered = render(generated, &written.cache, &output, format);
+            written.cache.record(&rendered.file, rendered.hash);
+            rendered.file
+        } else if symlink || !matches!(format, Format::Man | Format::Mdoc) {
+            remove_old();
+            let ext = if format.compressed() {
+                format!("{ext}.gz")
+            } else {
+                ext.to_string()
+            };
+
+            // relative, so that it keeps working once installed
+            let link = output.join(format!("{alias}.{ext}"));
+            let target = targets.first().unwrap();
+            symlink_file(format!("{target}.{ext}"), &link)
+                .fail("failed to create alias symlink");
+            link
+        } else {
+            // man(1) resolves `.so` relative to the root of the hierarchy
+            remove_old();
+            let path = output.join(format!("{alias}.{ext}"));
+            let target = targets.first().unwrap();
+            fs::write(&path, format!(".so man3/{target}.{ext}\n"))
+                .fail("failed to write alias page");
+            path
+        };
+
+        for target in &targets {
+            let krate = &written.pages[target];
+            written.files.get_mut(krate).unwrap().insert(file.clone());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink_file(original: String, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(original: String, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+/// Returns the target triple of the host, which is what rustdoc builds for.
+fn host() -> Option<String> {
+    let output = Command::new("rustc").arg("-vV").output().ok()?;
+    String::from_utf8(output.stdout)
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(String::from)
+}
+
+/// Builds rustdoc JSON for every package to document in a manifest, returning
+/// the paths to the JSON files. A workspace root yields all of its (library)
+/// members, filtered by `--package`/`--exclude`; anything else yields just its
+/// own package. With `--deps`, their dependencies are built as well. Each file
+/// comes with the features it was built with.
+fn build(args: &Args, file: &str) -> Vec<(PathBuf, Option<String>)> {
+    let mut data_dir = dirs::data_dir().unwrap_or_else(|| "./".into());
+    data_dir.push("manners");
+    fs::create_dir_all(&data_dir).fail("failed to create data directory");
+
+    let mut metadata = MetadataCommand::new();
+    metadata.manifest_path(file);
+    if !args.deps {
+        metadata.no_deps();
+    }
+    let metadata = metadata.exec().fail("failed to read cargo metadata");
+
+    let manifest = fs::canonicalize(file).fail("failed to find manifest");
+    let is_root = metadata.workspace_root.join("Cargo.toml").as_std_path() == manifest;
+
+    let members: Vec<_> = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|pkg| is_root || pkg.manifest_path.as_std_path() == manifest)
+        .filter(|pkg| {
+            pkg.targets.iter().any(|t| {
+                t.kind
+                    .iter()
+                    .any(|k| k.ends_with("lib") || k == "proc-macro")
+            })
+        })
+        .filter(|pkg| args.package.as_ref().is_none_or(|p| p.contains(&pkg.name)))
+        .filter(|pkg| !args.exclude.as_ref().is_some_and(|e| e.contains(&pkg.name)))
+        .collect();
+
+    if members.is_empty() {
+        eprintln!("no packages to document in {file}");
+    }
+
+    let features = match &args.features {
+        None => "all".to_string(),
+        Some(features) if features.is_empty() => "default".to_string(),
+        Some(features) => features.join(", "),
+    };
+
+    let mut docs: Vec<_> = members
+        .iter()
+        .map(|pkg| {
+            let path = rustdoc_json::Builder::default()
+                .toolchain("nightly")
+                .target_dir(&data_dir)
+                .all_features(args.features.is_none())
+                .no_default_features(args.features.as_ref().is_some_and(|f| !f.is_empty()))
+                .features(args.features.as_deref().unwrap_or(&[]))
+                .manifest_path(&pkg.manifest_path)
+                .build()
+                // TODO: rustdoc-json has terrible error practices
+                .fail("rustdoc-json failed");
+            (path, Some(features.clone()))
+        })
+        .collect();
+
+    if args.deps {
+        // dependencies get built through the workspace, with whatever
+        // features it resolved for them
+        for (dep, features) in dependencies(&metadata, &members) {
+            eprintln!("- documenting dependency {} {}", dep.name, dep.version);
+            let path = rustdoc_json::Builder::default()
+                .toolchain("nightly")
+                .target_dir(&data_dir)
+                .manifest_path(file)
+                .package(&dep.name)
+                .build()
+                .fail("rustdoc-json failed");
+
+            let features = if features.is_empty() {
+                "none".to_string()
+            } else {
+                features.join(", ")
+            };
+            docs.push((path, Some(features)));
+        }
+    }
+
+    docs
+}
+
+/// Collects the transitive normal dependencies of `members`, sorted by name,
+/// with the features resolved for them. Packages with several versions in the
+/// graph are skipped, since they can't be told apart by name.
+fn dependencies<'a>(
+    metadata: &'a Metadata,
+    members: &[&'a Package],
+) -> Vec<(&'a Package, &'a [String])> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .fail("cargo metadata didn't resolve dependencies");
+
+    let mut seen: HashSet<_> = members.iter().map(|pkg| &pkg.id).collect();
+    let mut queue: Vec<_> = seen.iter().copied().collect();
+    let mut deps = Vec::new();
+    while let Some(id) = queue.pop() {
+        let Some(node) = resolve.nodes.iter().find(|node| &node.id == id) else {
+            continue;
+        };
+
+        for dep in &node.deps {
+            let normal = dep
+                .dep_kinds
+                .iter()
+                .any(|info| info.kind == DependencyKind::Normal);
+
+            if normal && seen.insert(&dep.pkg) {
+                queue.push(&dep.pkg);
+                deps.push(&metadata[&dep.pkg]);
+            }
         }
     }
+
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut names = HashSet::new();
+    let duplicates: BTreeSet<_> = deps
+        .iter()
+        .filter(|dep| !names.insert(&dep.name))
+        .map(|dep| dep.name.clone())
+        .collect();
+    for name in &duplicates {
+        eprintln!("skipping dependency {name}: multiple versions in use");
+    }
+
+    deps.retain(|dep| {
+        !duplicates.contains(&dep.name) && !members.iter().any(|pkg| pkg.name == dep.name)
+    });
+
+    deps.into_iter()
+        .map(|dep| {
+            let node = resolve.nodes.iter().find(|node| node.id == dep.id);
+            (dep, node.map_or(&[][..], |node| node.features.as_slice()))
+        })
+        .collect()
 }
 
-fn save(page: Roff, mut path: PathBuf) {
-    let inner_file = path.display().to_string() + ".3r";
-    path.set_extension("3r.gz");
+/// Returns the path of the file a page gets written to.
+fn file_path(format: Format, mut path: PathBuf) -> PathBuf {
+    if format.compressed() {
+        path.set_extension(format!("{}.gz", format.extension()));
+    } else {
+        path.set_extension(format.extension());
+    }
+    path
+}
+
+/// Writes a page, returning the path of the file it went into.
+fn save(page: Page, format: Format, mut path: PathBuf) -> PathBuf {
+    if !format.compressed() {
+        path.set_extension(format.extension());
+        let mut file = fs::File::create(&path).fail("failed to create output file");
+        page.write(format, &mut file)
+            .fail("failed to write to output file");
+        return path;
+    }
+
+    let inner_file = path.display().to_string() + "." + format.extension();
+    path.set_extension(format!("{}.gz", format.extension()));
     let file = fs::File::create(&path).fail("failed to create output file");
     let mut gz = GzBuilder::new()
         .filename(inner_file)
         .write(file, Compression::default());
 
-    page.to_writer(&mut gz)
+    page.write(format, &mut gz)
         .fail("failed to write to output file");
 
     gz.finish().fail("failed to compress");
+    path
 }
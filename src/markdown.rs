@@ -1,5 +1,31 @@
-use markdown::{mdast::Node, ParseOptions};
-use roff::{bold, italic, line_break, roman, Inline};
+use markdown::{
+    mdast::{AlignKind, ListItem, Node},
+    ParseOptions,
+};
+use crate::gen::format::{
+    bold, control, italic, line_break, link_end, link_start, roman, table, Align, Inline,
+};
+use crate::gen::Highlight;
+
+mod highlight;
+
+use std::{borrow::Cow, collections::HashMap};
+
+/// How far the footnotes listed under `NOTES` are indented, past their labels.
+pub const NOTE_INDENT: usize = 6;
+
+/// Resolves intra-doc links and footnotes to reference numbers.
+pub trait Links {
+    /// Every link destination rustdoc resolved, as written in the docs.
+    fn destinations(&self) -> Vec<&str>;
+
+    /// Returns the reference number of a destination, if it leads to a page.
+    fn number(&self, destination: &str) -> Option<usize>;
+
+    /// Returns the number of a footnote, given its rendered text, numbering it
+    /// if it's new to the page.
+    fn footnote(&self, label: &str, text: Vec<Inline>) -> usize;
+}
 
 enum List {
     Simple,
@@ -11,24 +37,54 @@ impl List {
         match self {
             List::Simple => "-".to_string(),
             List::Numbered(i) => {
-                let x = i.to_string();
+                let x = format!("{i}.");
                 *i += 1;
                 x
             }
         }
     }
+
+    /// How far the items are indented, past their bullets.
+    fn width(&self) -> usize {
+        match self {
+            List::Simple => 2,
+            List::Numbered(_) => 4,
+        }
+    }
 }
 
-#[derive(Default)]
-struct State {
+struct State<'a> {
     bold: bool,
     italic: bool,
-    start: bool,
-    indentation: usize,
-    list_bullets: Vec<List>,
+    /// Whether the next block is the first in its container, so that it needs
+    /// no paragraph break.
+    first: bool,
+    /// The indentation of the list item whose blocks are being rendered.
+    item: Option<usize>,
+    links: &'a dyn Links,
+    /// Link reference definitions, by identifier.
+    definitions: HashMap<String, String>,
+    /// Footnote definitions, by identifier.
+    footnotes: HashMap<String, Node>,
+    highlight: Highlight,
+    /// Whether headings become subsections (`SS`), which they only can outside
+    /// of any indentation, and under a section rather than a subsection.
+    subsections: bool,
 }
 
-impl State {
+impl State<'_> {
+    /// Returns the URL a link leads to, if it's external.
+    fn url<'b>(&'b self, node: &'b Node) -> Option<&'b str> {
+        let url = match node {
+            Node::Link(link) => &link.url,
+            Node::LinkReference(link) => self.definitions.get(&link.identifier)?,
+            _ => return None,
+        };
+
+        // anything else is an intra-doc link, or relative to the docs' HTML
+        (url.contains("://") || url.starts_with("mailto:")).then_some(url.as_str())
+    }
+
     fn fmt(&self, s: impl ToString) -> Inline {
         let s = s.to_string();
         if self.bold {
@@ -39,108 +95,463 @@ impl State {
             roman(s)
         }
     }
+
+    /// Starts a new block, breaking the paragraph unless it's the first one.
+    fn block(&mut self, inline: &mut Vec<Inline>) {
+        if !self.first {
+            match self.item {
+                // a new paragraph would lose the indentation of the item
+                Some(width) => inline.push(control("IP", ["", &width.to_string()])),
+                None => inline.push(control("PP", [])),
+            }
+        }
+        self.first = false;
+    }
+
+    /// Renders a footnote, for the `NOTES` at the end of the page, and returns
+    /// its number.
+    fn footnote(&mut self, identifier: &str) -> Option<usize> {
+        // taken out while it's rendered, in case it refers to itself
+        let node = self.footnotes.remove(identifier)?;
+
+        let outer = (self.bold, self.italic, self.first, self.item);
+        (self.bold, self.italic, self.first, self.item) = (false, false, true, Some(NOTE_INDENT));
+        let subsections = std::mem::take(&mut self.subsections);
+        let mut text = Vec::new();
+        for child in node.children().into_iter().flatten() {
+            traverse_nodes(child, &mut text, self);
+        }
+        (self.bold, self.italic, self.first, self.item) = outer;
+        self.subsections = subsections;
+
+        let n = self.links.footnote(identifier, text);
+        self.footnotes.insert(identifier.to_string(), node);
+        Some(n)
+    }
+
+    /// Renders the text of a table cell, which has to fit on one line.
+    fn cell(&mut self, cell: &Node) -> Vec<Inline> {
+        let mut text = Vec::new();
+        for child in cell.children().into_iter().flatten() {
+            traverse_nodes(child, &mut text, self);
+        }
+
+        let line = |s: String| s.replace(['\t', '\n'], " ");
+        text.into_iter()
+            .filter_map(|inline| match inline {
+                Inline::Roman(s) => Some(Inline::Roman(line(s))),
+                Inline::Italic(s) => Some(Inline::Italic(line(s))),
+                Inline::Bold(s) => Some(Inline::Bold(line(s))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Renders the children of a block quote or list, which get indented by
+    /// `indent` more than the blocks around them.
+    fn nest(&mut self, node: &Node, indent: usize, inline: &mut Vec<Inline>) {
+        let indent = self.item.unwrap_or_default() + indent;
+        if indent > 0 {
+            inline.push(control("RS", [indent.to_string().as_str()]));
+        }
+
+        let item = self.item.take();
+        let subsections = std::mem::take(&mut self.subsections);
+        self.first = true;
+        match node {
+            Node::List(list) => {
+                let mut bullets = list.start.map(List::Numbered).unwrap_or(List::Simple);
+                let width = bullets.width().to_string();
+                for child in &list.children {
+                    inline.push(control("IP", [bullets.bullet().as_str(), &width]));
+                    self.item = Some(bullets.width());
+                    self.first = true;
+
+                    if let Node::ListItem(ListItem {
+                        checked: Some(checked),
+                        ..
+                    }) = child
+                    {
+                        inline.push(roman(if *checked { "[x] " } else { "[ ] " }));
+                    }
+                    for child in child.children().into_iter().flatten() {
+                        traverse_nodes(child, inline, self);
+                    }
+                }
+            }
+            _ => {
+                for child in node.children().into_iter().flatten() {
+                    traverse_nodes(child, inline, self);
+                }
+            }
+        }
+        self.item = item;
+        self.subsections = subsections;
+        self.first = false;
+
+        if indent > 0 {
+            inline.push(control("RE", []));
+        }
+    }
 }
 
 fn traverse_nodes(node: &Node, inline: &mut Vec<Inline>, state: &mut State) {
     match node {
-        Node::BlockQuote(_) => state.indentation += 2,
-        Node::List(list) => {
-            state.indentation += 1;
-            let bullet = list.start.map(List::Numbered).unwrap_or(List::Simple);
-            state.list_bullets.push(bullet);
+        Node::BlockQuote(_) => {
+            state.block(inline);
+            return state.nest(node, 4, inline);
+        }
+        // the items' own paragraphs separate them from what's before
+        Node::List(_) => return state.nest(node, 0, inline),
+        Node::Table(t) => {
+            state.block(inline);
+            let align = t
+                .align
+                .iter()
+                .map(|align| match align {
+                    AlignKind::Center => Align::Center,
+                    AlignKind::Right => Align::Right,
+                    AlignKind::Left | AlignKind::None => Align::Left,
+                })
+                .collect();
+            let rows = t
+                .children
+                .iter()
+                .map(|row| {
+                    let cells = row.children().into_iter().flatten();
+                    cells.map(|cell| state.cell(cell)).collect()
+                })
+                .collect();
+            return inline.push(table(align, rows));
+        }
+        // listed under `NOTES` instead, once they're referred to
+        Node::FootnoteDefinition(_) => return,
+        Node::FootnoteReference(note) => match state.footnote(&note.identifier) {
+            Some(n) => inline.push(roman(format!("[^{n}]"))),
+            None => inline.push(state.fmt(format!("[^{}]", note.identifier))),
+        },
+        // with enough backticks around it for any in the code
+        Node::InlineCode(code) => {
+            let mut ticks = "`".to_string();
+            while code.value.contains(&ticks) {
+                ticks.push('`');
+            }
+            let pad = if ticks.len() > 1 { " " } else { "" };
+            inline.push(state.fmt(format!("{ticks}{pad}{}{pad}{ticks}", code.value)));
         }
-        Node::InlineCode(code) => inline.push(state.fmt(format!("`{}`", code.value))),
         Node::Delete(_) => inline.push(state.fmt("~~")),
         Node::Emphasis(_) => state.italic = true,
-        // TODO: improve link handling
-        // Node::Link(_) => inline.push(state.fmt("  ".repeat(state.indentation) + "[")),
         Node::Strong(_) => state.bold = true,
-        Node::Heading(h) => {
-            inline.push(line_break());
-            inline.push(state.fmt(" "));
-            inline.push(state.fmt("  ".repeat(state.indentation)));
-            inline.push(state.fmt("#".repeat(h.depth as usize)));
-            inline.push(state.fmt(" "));
+        Node::Link(_) | Node::LinkReference(_) => {
+            if let Some(url) = state.url(node) {
+                inline.push(link_start(url));
+            }
+        }
+        Node::Heading(_) if state.subsections => {
+            let mut text = String::new();
+            collect_text(node, &mut text);
+            inline.push(control("SS", [text.trim()]));
+            state.first = true;
+            return;
+        }
+        Node::Heading(_) => {
+            state.block(inline);
             state.bold = true;
         }
         Node::Text(s) => inline.push(state.fmt(&s.value)),
         Node::Code(code) => {
-            inline.push(line_break());
-            inline.push(line_break());
+            state.block(inline);
+            let indent = state.item.unwrap_or_default() + 2;
+            inline.push(control("RS", [indent.to_string().as_str()]));
+
+            let tags = rust_tags(code.lang.as_deref(), code.meta.as_deref());
+            if let Some(tags) = tags.as_ref().filter(|tags| !tags.is_empty()) {
+                inline.push(italic(format!("({})", tags.join(", "))));
+            }
+            let lang = match &tags {
+                Some(_) => "rust",
+                None => code.lang.as_deref().unwrap_or_default(),
+            };
+            inline.push(control("EX", [lang].into_iter().filter(|lang| !lang.is_empty())));
 
-            for line in code.value.lines() {
-                if line.trim().starts_with("# ") || line.trim() == "#" {
-                    continue;
+            // only Rust code has hidden lines; `#` starts comments elsewhere
+            let lines: Vec<_> = match tags {
+                Some(_) => code.value.lines().filter_map(rust_line).collect(),
+                None => code.value.lines().map(Cow::from).collect(),
+            };
+            if tags.is_some() && state.highlight != Highlight::None {
+                inline.append(&mut highlight::rust(&lines.join("\n"), state.highlight));
+            } else {
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        inline.push(line_break());
+                    }
+                    inline.push(state.fmt(line));
                 }
-                let indent = "  ".repeat(state.indentation + 1);
-                inline.push(state.fmt(indent));
-                inline.push(state.fmt(line));
-                inline.push(line_break());
             }
 
-            // inline.push(line_break());
-            inline.push(line_break());
+            inline.push(control("EE", []));
+            inline.push(control("RE", []));
+        }
+        Node::Paragraph(_) | Node::ThematicBreak(_) => state.block(inline),
+        _ => {}
+    }
+
+    for child in node.children().into_iter().flatten() {
+        traverse_nodes(child, inline, state);
+    }
+
+    match node {
+        Node::Delete(_) => inline.push(state.fmt("~~")),
+        Node::Emphasis(_) => state.italic = false,
+        Node::Link(_) | Node::LinkReference(_) if state.url(node).is_some() => {
+            inline.push(link_end());
+        }
+        Node::Link(link) => {
+            if let Some(n) = state.links.number(&link.url) {
+                inline.push(roman(format!("[{n}]")));
+            }
         }
-        Node::Paragraph(_) => {
-            if state.start {
-                let indent = "  ".repeat(state.indentation);
-                inline.push(state.fmt(indent));
+        Node::LinkReference(link) => {
+            let url = state.definitions.get(&link.identifier);
+            if let Some(n) = url.and_then(|url| state.links.number(url)) {
+                inline.push(roman(format!("[{n}]")));
             }
+        }
+        Node::Strong(_) | Node::Heading(_) => state.bold = false,
+        _ => {}
+    }
+}
+
+/// Returns the tags shown above a Rust code block for the attributes in its info
+/// string, or `None` if it's in another language. Like rustdoc, any attribute
+/// it doesn't know makes it another language, unless there's also a `rust`.
+fn rust_tags(lang: Option<&str>, meta: Option<&str>) -> Option<Vec<String>> {
+    let info = [lang, meta].into_iter().flatten().collect::<Vec<_>>().join(" ");
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
 
-            state.start = false;
+    let (mut rust, mut other) = (false, false);
+    let mut tags = Vec::new();
+    for attr in info.split([',', ' ', '\t']).filter(|attr| !attr.is_empty()) {
+        match attr {
+            "rust" => rust = true,
+            "compile_fail" => tags.push("does not compile".to_string()),
+            "should_panic" => tags.push("panics".to_string()),
+            "no_run" => tags.push("not run".to_string()),
+            "ignore" => tags.push("not tested".to_string()),
+            "test_harness" | "standalone_crate" => {}
+            _ if attr.strip_prefix("edition").is_some_and(digits) => {
+                tags.push(format!("edition {}", &attr["edition".len()..]));
+            }
+            _ if attr.starts_with("ignore-") => {
+                tags.push(format!("not tested on {}", &attr["ignore-".len()..]));
+            }
+            // the error codes a `compile_fail` example is expected to fail with
+            _ if attr.strip_prefix('E').is_some_and(digits) => {}
+            _ => other = true,
         }
-        Node::ListItem(_) => {
-            let indent = "  ".repeat(state.indentation);
-            inline.push(state.fmt(indent));
+    }
 
-            let bullet = state.list_bullets.last_mut().unwrap().bullet();
-            inline.push(state.fmt(bullet));
-            inline.push(state.fmt(" "));
+    (rust || !other).then_some(tags)
+}
+
+/// Returns how a line of a Rust code block is shown, or `None` if it's hidden
+/// with a leading `# `. A leading `##` is shown as a single `#`.
+fn rust_line(line: &str) -> Option<Cow<'_, str>> {
+    let trimmed = line.trim();
+    if trimmed.starts_with("##") {
+        Some(line.replacen("##", "#", 1).into())
+    } else if trimmed.starts_with("# ") || trimmed == "#" {
+        None
+    } else {
+        Some(line.into())
+    }
+}
 
-            state.start = false;
+fn collect_definitions(
+    node: &Node,
+    definitions: &mut HashMap<String, String>,
+    footnotes: &mut HashMap<String, Node>,
+) {
+    // the first definition of a label wins
+    match node {
+        Node::Definition(def) => {
+            definitions
+                .entry(def.identifier.clone())
+                .or_insert_with(|| def.url.clone());
+        }
+        Node::FootnoteDefinition(def) => {
+            footnotes
+                .entry(def.identifier.clone())
+                .or_insert_with(|| node.clone());
         }
-        Node::ThematicBreak(_) => inline.push(line_break()),
         _ => {}
     }
 
     for child in node.children().into_iter().flatten() {
-        traverse_nodes(child, inline, state);
+        collect_definitions(child, definitions, footnotes);
+    }
+}
+
+fn parse(markdown: &str, links: &dyn Links) -> Node {
+    // rustdoc resolves shortcut links like `[Vec]` without any definition,
+    // but markdown would leave them as plain text
+    let mut markdown = close_fence(markdown) + "\n";
+    for dest in links.destinations() {
+        let label = dest.replace('[', "\\[").replace(']', "\\]");
+        let url = dest.replace('<', "\\<").replace('>', "\\>");
+        markdown += &format!("\n[{label}]: <{url}>");
     }
 
+    markdown::to_mdast(&markdown, &ParseOptions::gfm()).unwrap()
+}
+
+/// Closes the code fence that markdown ends in, if it's left open (which ends
+/// the code block at the end of the docs), so that nothing appended to the
+/// markdown ends up in the code.
+pub fn close_fence(markdown: &str) -> String {
+    let root = markdown::to_mdast(markdown, &ParseOptions::gfm()).unwrap();
+
+    let mut last = &root;
+    while let Some(child) = last.children().and_then(|children| children.last()) {
+        last = child;
+    }
+    let (Node::Code(_), Some(pos)) = (last, last.position()) else {
+        return markdown.to_string();
+    };
+
+    let code = &markdown[pos.start.offset..pos.end.offset];
+    let Some(c) = code.chars().next().filter(|c| ['`', '~'].contains(c)) else {
+        // indented code has no fence to close
+        return markdown.to_string();
+    };
+    let fence = &code[..code.len() - code.trim_start_matches(c).len()];
+
+    // the closing fence can be indented, or inside a block quote
+    let closing = code.lines().last().map(|l| l.trim_start_matches([' ', '>']).trim_end());
+    let closed = closing.is_some_and(|l| l.starts_with(fence) && l.chars().all(|x| x == c));
+    if code.lines().count() > 1 && closed {
+        markdown.to_string()
+    } else {
+        format!("{markdown}\n{fence}")
+    }
+}
+
+fn collect_text(node: &Node, text: &mut String) {
     match node {
-        Node::BlockQuote(_) => state.indentation -= 2,
-        Node::List(_) => {
-            state.indentation -= 1;
-            state.list_bullets.pop();
-            state.start = true;
-        }
-        Node::ListItem(_) => state.start = true,
-        Node::Delete(_) => inline.push(state.fmt("~~")),
-        Node::Emphasis(_) => state.italic = false,
-        // Node::Link(link) => inline.push(state.fmt(format!("]({})", link.url))),
-        Node::Strong(_) => state.bold = false,
-        Node::Heading(_) => {
-            state.bold = false;
-            inline.push(line_break());
-            state.start = true;
+        Node::Text(t) => text.push_str(&t.value),
+        Node::InlineCode(code) => text.push_str(&code.value),
+        _ => {}
+    }
+
+    for child in node.children().into_iter().flatten() {
+        collect_text(child, text);
+    }
+
+    // keep blocks from running into each other
+    if matches!(
+        node,
+        Node::Paragraph(_) | Node::Heading(_) | Node::ListItem(_) | Node::Break(_)
+    ) {
+        text.push(' ');
+    }
+}
+
+/// A part of some docs, under one of their top-level headings.
+pub struct Section {
+    /// The text of the heading, or `None` for what comes before the first one.
+    pub heading: Option<String>,
+    pub markdown: String,
+}
+
+/// Returns the link reference and footnote definitions of some docs, which
+/// apply to every part of them.
+pub fn definitions(markdown: &str) -> String {
+    let root = markdown::to_mdast(markdown, &ParseOptions::gfm()).unwrap();
+
+    let mut definitions = String::new();
+    for node in root.children().into_iter().flatten() {
+        if let (Node::Definition(_) | Node::FootnoteDefinition(_), Some(pos)) =
+            (node, node.position())
+        {
+            definitions += &markdown[pos.start.offset..pos.end.offset];
+            definitions += "\n\n";
         }
-        Node::Paragraph(_) | Node::ThematicBreak(_) => {
-            inline.push(line_break());
-            state.start = true;
+    }
+
+    definitions
+}
+
+/// Splits docs at their top-level (`#`) headings, like the `# Examples` most
+/// Rust docs have. Any [`definitions`] stay in the section they're written in.
+pub fn sections(markdown: &str) -> Vec<Section> {
+    let root = markdown::to_mdast(markdown, &ParseOptions::gfm()).unwrap();
+    let children = root.children().map(Vec::as_slice).unwrap_or_default();
+
+    let mut sections = Vec::new();
+    let mut heading = None;
+    let mut start = 0;
+    for node in children {
+        let (Node::Heading(h), Some(pos)) = (node, node.position()) else {
+            continue;
+        };
+        if h.depth != 1 {
+            continue;
         }
-        _ => state.start = true,
+
+        sections.push(Section {
+            markdown: markdown[start..pos.start.offset].trim_end().to_string(),
+            heading: heading.take(),
+        });
+
+        let mut text = String::new();
+        collect_text(node, &mut text);
+        heading = Some(text.trim().to_string());
+        start = pos.end.offset;
     }
+
+    sections.push(Section {
+        markdown: markdown[start..].trim_end().to_string(),
+        heading,
+    });
+
+    sections
 }
 
-pub fn to_roff(markdown: &str, indentation: usize) -> Vec<Inline> {
+/// Renders markdown as a single line of plain text, e.g. for `NAME`.
+pub fn to_plain(markdown: &str, links: &dyn Links) -> String {
+    let mut text = String::new();
+    collect_text(&parse(markdown, links), &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Renders markdown as text lines, with the man(7) macros to lay out its blocks.
+/// With `subsections`, its headings start subsections; otherwise they're bold.
+pub fn to_roff(
+    markdown: &str,
+    links: &dyn Links,
+    highlight: Highlight,
+    subsections: bool,
+) -> Vec<Inline> {
     let mut inline = Vec::new();
-    let root = markdown::to_mdast(markdown, &ParseOptions::default()).unwrap();
+    let root = parse(markdown, links);
+
+    let (mut definitions, mut footnotes) = (HashMap::new(), HashMap::new());
+    collect_definitions(&root, &mut definitions, &mut footnotes);
+
     traverse_nodes(
         &root,
         &mut inline,
         &mut State {
-            indentation,
-            ..State::default()
+            bold: false,
+            italic: false,
+            first: true,
+            item: None,
+            links,
+            definitions,
+            footnotes,
+            highlight,
+            subsections,
         },
     );
     inline
@@ -0,0 +1,174 @@
+use crate::gen::format::{bold, color_end, color_start, italic, line_break, roman, Color, Inline};
+use crate::gen::Highlight;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f32", "f64",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Plain,
+    Keyword,
+    Type,
+    Comment,
+}
+
+/// Renders Rust code with its keywords, types and comments set apart, as text
+/// lines joined by line breaks.
+pub fn rust(code: &str, highlight: Highlight) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for (kind, token) in tokens(code) {
+        // comments and strings can go on for several lines
+        for (i, line) in token.split('\n').enumerate() {
+            if i > 0 {
+                inlines.push(line_break());
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let (text, color) = match kind {
+                Kind::Plain => {
+                    match inlines.last_mut() {
+                        Some(Inline::Roman(s)) => s.push_str(line),
+                        _ => inlines.push(roman(line)),
+                    }
+                    continue;
+                }
+                Kind::Keyword => (bold(line), Color::Magenta),
+                Kind::Type => (italic(line), Color::Green),
+                Kind::Comment => (italic(line), Color::Blue),
+            };
+
+            if highlight == Highlight::Colors {
+                inlines.extend([color_start(color), text, color_end()]);
+            } else {
+                inlines.push(text);
+            }
+        }
+    }
+
+    inlines
+}
+
+/// Splits code into the tokens that are highlighted, and the plain text around
+/// them.
+fn tokens(code: &str) -> Vec<(Kind, &str)> {
+    let ident_len = |s: &str| {
+        s.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(s.len())
+    };
+
+    let mut tokens = Vec::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (kind, len) = if rest.starts_with("//") {
+            (Kind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (Kind::Comment, block_comment(rest))
+        } else if let Some(len) = string(rest) {
+            (Kind::Plain, len)
+        } else if c == '\'' {
+            (Kind::Plain, quote(rest))
+        } else if let Some(raw) = rest.strip_prefix("r#") {
+            // raw identifiers are never keywords
+            (Kind::Plain, 2 + ident_len(raw))
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = ident_len(rest);
+            let word = &rest[..len];
+            let kind = if KEYWORDS.contains(&word) {
+                Kind::Keyword
+            } else if PRIMITIVES.contains(&word) || c.is_uppercase() {
+                Kind::Type
+            } else {
+                // this includes numbers, along with any suffix like `1u8`
+                Kind::Plain
+            };
+            (kind, len)
+        } else {
+            (Kind::Plain, c.len_utf8())
+        };
+
+        tokens.push((kind, &rest[..len]));
+        rest = &rest[len..];
+    }
+
+    tokens
+}
+
+/// Returns the length of the block comment at the start of `s`, which can nest.
+fn block_comment(s: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if s[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += s[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    s.len()
+}
+
+/// Returns the length of the (byte, C or raw) string literal at the start of
+/// `s`, if there is one.
+fn string(s: &str) -> Option<usize> {
+    let body = s.strip_prefix(['b', 'c']).unwrap_or(s);
+
+    if let Some(raw) = body.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let quoted = raw[hashes..].strip_prefix('"')?;
+        let end = format!("\"{}", "#".repeat(hashes));
+        let len = quoted.find(&end).map_or(quoted.len(), |i| i + end.len());
+        return Some(s.len() - quoted.len() + len);
+    }
+
+    let quoted = body.strip_prefix('"')?;
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return Some(s.len() - quoted.len() + i + 1),
+            _ => {}
+        }
+    }
+
+    Some(s.len())
+}
+
+/// Returns the length of the char literal or lifetime at the start of `s`.
+fn quote(s: &str) -> usize {
+    let rest = &s[1..];
+    match rest.chars().next() {
+        // the escaped char may be a quote itself
+        Some('\\') => rest
+            .get(2..)
+            .and_then(|escaped| escaped.find('\''))
+            .map_or(1, |i| i + 4),
+        Some(c) if rest[c.len_utf8()..].starts_with('\'') => c.len_utf8() + 2,
+        _ => {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            len + 1
+        }
+    }
+}